@@ -1,6 +1,16 @@
 use clap::{Parser, Subcommand};
 
-use crate::job::event::{RestartBehavior, RestartStrategy};
+use crate::{
+    colors::JobColor,
+    job::{
+        Job,
+        event::{Action, Event, Hook, RestartBehavior, RestartStrategy, Stream},
+        format::Format,
+        output::{OutputMode, OutputOptions},
+        project::Sources,
+        show::ShowFormat,
+    },
+};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -17,6 +27,14 @@ pub struct Cli {
 
     #[arg(long, help = "Disable colored output")]
     pub no_color: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Save and load jobs in this directory instead of $TEND_HOME/jobs [default: ~/.tend/jobs]"
+    )]
+    pub jobs_dir: Option<std::path::PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -53,10 +71,15 @@ pub enum Commands {
         job: Vec<String>,
         #[arg(alias = "except", short, long, help = "Omit the given job(s)", num_args = 1.., use_value_delimiter = true)]
         exclude: Vec<String>,
+        #[command(flatten)]
+        sources: JobSources,
     },
     #[command(alias = "r", alias = "start", about = "Run saved jobs")]
     Run {
-        #[arg(help = "Job name to run", exclusive = true)]
+        #[arg(
+            help = "Job name to run",
+            conflicts_with_all = ["all", "group", "job", "exclude"]
+        )]
         name: Option<String>,
         #[arg(
             short,
@@ -86,41 +109,47 @@ pub enum Commands {
         job: Vec<String>,
         #[arg(alias = "except", short, long, help = "Omit the given job(s)", num_args = 1.., use_value_delimiter = true)]
         exclude: Vec<String>,
+        #[command(flatten)]
+        options: RunOptions,
     },
     #[command(alias = "c", alias = "new", about = "Create a job")]
     Create {
-        #[arg(help = "Unique name for the job")]
+        #[arg(help = "Unique name for the job", value_parser = crate::job::name::parse)]
         name: String,
-        #[arg(help = "Executable to run")]
-        program: String,
+        #[arg(long, short = 'w', help = "Overwrite existing job with the same name")]
+        overwrite: bool,
+        #[command(flatten)]
+        job: JobArgs,
+    },
+    #[command(
+        alias = "x",
+        about = "Run a command with the usual supervision, without saving it as a job"
+    )]
+    Exec {
         #[arg(
             long,
-            default_value = "always",
-            short = 'r',
-            help = "When to restart the job after it exits"
+            short = 'n',
+            help = "Job name shown in output [default: program name]"
         )]
-        restart: RestartBehavior,
+        name: Option<String>,
         #[arg(
             long,
-            default_value = "exponential-backoff",
-            help = "How long to wait between automatic restarts"
+            value_name = "NAME",
+            value_parser = crate::job::name::parse,
+            help = "Also save the command as a job"
         )]
-        restart_strategy: RestartStrategy,
-        #[arg(long, short = 'w', help = "Overwrite existing job with the same name")]
-        overwrite: bool,
+        save: Option<String>,
         #[arg(
             long,
-            short = 'g',
-            help = "Assign the job to a group",
-            default_value = "default"
-        )]
-        group: String,
-        #[arg(long, short = 't', help = "Apply a predefined job template")]
-        template: Option<crate::job::template::Template>,
-        #[arg(
-            help = "Arguments passed to the program; use -- before program args that begin with a dash"
+            short = 'w',
+            requires = "save",
+            help = "Overwrite existing job with the same name when saving"
         )]
-        args: Vec<String>,
+        overwrite: bool,
+        #[arg(long, default_value = "text", help = "How to print output and events")]
+        output: OutputMode,
+        #[command(flatten)]
+        job: JobArgs,
     },
     #[command(about = "Enable jobs so they can run")]
     Enable {
@@ -195,6 +224,143 @@ pub enum Commands {
         #[command(subcommand)]
         command: EditJobCommands,
     },
+    #[command(about = "Convert saved jobs to another file format")]
+    Convert {
+        #[arg(
+            help = "Job name to convert",
+            conflicts_with_all = ["all", "group", "job", "exclude"]
+        )]
+        name: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Convert all jobs",
+            conflicts_with = "group",
+            conflicts_with = "job"
+        )]
+        all: bool,
+        #[arg(
+            short,
+            long,
+            help = "Convert jobs in the given group(s)",
+            num_args = 1..,
+            conflicts_with = "all",
+            use_value_delimiter = true
+        )]
+        group: Vec<String>,
+        #[arg(
+            short,
+            long,
+            help = "Convert the given job(s)",
+            num_args = 1..,
+            conflicts_with = "all",
+            use_value_delimiter = true
+        )]
+        job: Vec<String>,
+        #[arg(alias = "except", short, long, help = "Omit the given job(s)", num_args = 1.., use_value_delimiter = true)]
+        exclude: Vec<String>,
+        #[arg(long, help = "File format to convert the jobs to")]
+        to: Format,
+    },
+    #[command(about = "Upgrade saved jobs to the current job file schema")]
+    Migrate {
+        #[arg(
+            help = "Job name to migrate",
+            conflicts_with_all = ["all", "group", "job", "exclude"]
+        )]
+        name: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Migrate all jobs",
+            conflicts_with = "group",
+            conflicts_with = "job"
+        )]
+        all: bool,
+        #[arg(
+            short,
+            long,
+            help = "Migrate jobs in the given group(s)",
+            num_args = 1..,
+            conflicts_with = "all",
+            use_value_delimiter = true
+        )]
+        group: Vec<String>,
+        #[arg(
+            short,
+            long,
+            help = "Migrate the given job(s)",
+            num_args = 1..,
+            conflicts_with = "all",
+            use_value_delimiter = true
+        )]
+        job: Vec<String>,
+        #[arg(alias = "except", short, long, help = "Omit the given job(s)", num_args = 1.., use_value_delimiter = true)]
+        exclude: Vec<String>,
+        #[arg(long, help = "Only report what would change, without saving")]
+        dry_run: bool,
+    },
+    #[command(alias = "t", about = "List and show job templates")]
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    #[command(about = "Print the JSON Schema of job files")]
+    Schema {
+        #[arg(long, help = "Print the schema of tend.toml project files instead")]
+        project: bool,
+        #[arg(
+            long,
+            conflicts_with = "project",
+            help = "Print the schema of template files instead"
+        )]
+        template: bool,
+    },
+    #[command(alias = "mv", about = "Rename a job")]
+    Rename {
+        #[arg(help = "Job name to rename")]
+        name: String,
+        #[arg(help = "New name for the job", value_parser = crate::job::name::parse)]
+        new_name: String,
+        #[arg(long, short = 'w', help = "Overwrite existing job with the new name")]
+        overwrite: bool,
+    },
+    #[command(alias = "cp", about = "Copy a job under a new name")]
+    Clone {
+        #[arg(help = "Job name to copy")]
+        name: String,
+        #[arg(help = "Unique name for the copy", value_parser = crate::job::name::parse)]
+        new_name: String,
+        #[arg(long, short = 'w', help = "Overwrite existing job with the new name")]
+        overwrite: bool,
+        #[arg(long, short = 'g', help = "Assign the copy to a group")]
+        group: Option<String>,
+        #[arg(long, help = "Executable the copy runs")]
+        program: Option<String>,
+        #[arg(long, help = "Working directory of the copy")]
+        cwd: Option<std::path::PathBuf>,
+        #[arg(long, help = "Save the copy disabled")]
+        disabled: bool,
+        #[arg(
+            help = "Arguments passed to the program instead of the original ones; use -- before args that begin with a dash"
+        )]
+        args: Option<Vec<String>>,
+    },
+    #[command(alias = "s", about = "Show the complete definition of a job")]
+    Show {
+        #[arg(help = "Job name to show")]
+        name: String,
+        #[arg(long, short, default_value = "human", help = "Output format")]
+        format: ShowFormat,
+    },
+    #[command(
+        alias = "o",
+        about = "Open a job in $VISUAL or $EDITOR and save it once it is valid"
+    )]
+    Open {
+        #[arg(help = "Job name to open")]
+        name: String,
+    },
     #[command(alias = "d", alias = "rm", about = "Delete jobs")]
     #[clap(group(clap::ArgGroup::new("input").required(true).args(&["name", "group", "job", "all"])))]
     Delete {
@@ -233,6 +399,22 @@ pub enum Commands {
     },
 }
 
+#[derive(Clone, Debug, Subcommand)]
+pub enum TemplateCommands {
+    #[command(
+        alias = "ls",
+        about = "List built-in templates and templates in $TEND_HOME/templates"
+    )]
+    List,
+    #[command(about = "Show the complete definition of a template")]
+    Show {
+        #[arg(help = "Template name to show", value_parser = crate::job::name::parse_template)]
+        name: String,
+        #[arg(long, short, default_value = "human", help = "Output format")]
+        format: ShowFormat,
+    },
+}
+
 #[derive(Clone, Debug, Subcommand)]
 pub enum EditJobCommands {
     #[command(about = "Change the group of a job")]
@@ -240,6 +422,75 @@ pub enum EditJobCommands {
         #[arg(help = "New group name")]
         group: String,
     },
+    #[command(about = "Change the executable of a job")]
+    Program {
+        #[arg(help = "New executable to run")]
+        program: String,
+    },
+    #[command(about = "Change the arguments passed to the program")]
+    Args {
+        #[command(subcommand)]
+        command: EditJobArgsCommands,
+    },
+    #[command(about = "Change the working directory of a job")]
+    Cwd {
+        #[arg(help = "New working directory, relative to the current directory")]
+        path: std::path::PathBuf,
+    },
+    #[command(about = "Change when the job restarts after it exits")]
+    Restart {
+        #[arg(help = "When to restart the job after it exits")]
+        restart: RestartBehavior,
+    },
+    #[command(about = "Change how long to wait between automatic restarts")]
+    RestartStrategy {
+        #[arg(help = "How long to wait between automatic restarts")]
+        restart_strategy: RestartStrategy,
+    },
+    #[command(about = "Change or remove the template of a job, applying its restart settings")]
+    Template {
+        #[arg(
+            help = "Template to apply; omit to remove the current template",
+            value_parser = crate::job::name::parse_template
+        )]
+        template: Option<String>,
+        #[arg(
+            long,
+            value_name = "NAME=VALUE",
+            value_parser = parse_param,
+            requires = "template",
+            help = "Set a parameter of the template; parameters not given are reset"
+        )]
+        param: Vec<(String, String)>,
+    },
+    #[command(about = "Set or remove environment variables of a job")]
+    Env {
+        #[arg(
+            value_name = "KEY=VALUE",
+            value_parser = parse_env,
+            help = "Environment variables to set"
+        )]
+        set: Vec<(String, String)>,
+        #[arg(long, value_name = "KEY", help = "Environment variables to remove")]
+        unset: Vec<String>,
+    },
+    #[command(about = "Change the maximum length of output lines")]
+    MaxLineLength {
+        #[arg(help = "Maximum line length in bytes; omit to use the default")]
+        max_line_length: Option<usize>,
+    },
+    #[command(about = "Change the color of the job name in output")]
+    Color {
+        #[arg(help = "Color of the job name; omit to pick one based on the name")]
+        color: Option<JobColor>,
+    },
+    #[command(about = "Change how the output of the job is formatted")]
+    Output {
+        #[arg(long, help = "Reset the output options before applying the given ones")]
+        reset: bool,
+        #[command(flatten)]
+        output: OutputOptions,
+    },
     #[command(about = "Manage hooks for a job")]
     Hook {
         #[command(subcommand)]
@@ -247,6 +498,34 @@ pub enum EditJobCommands {
     },
 }
 
+#[derive(Clone, Debug, Subcommand)]
+pub enum EditJobArgsCommands {
+    #[command(
+        about = "Add arguments after the current ones",
+        override_usage = "tend edit <NAME> args append [ARGS]..."
+    )]
+    Append {
+        #[arg(
+            help = "Arguments to add",
+            allow_hyphen_values = true,
+            trailing_var_arg = true
+        )]
+        args: Vec<String>,
+    },
+    #[command(
+        about = "Replace all arguments",
+        override_usage = "tend edit <NAME> args replace [ARGS]..."
+    )]
+    Replace {
+        #[arg(
+            help = "New arguments; omit to remove all arguments",
+            allow_hyphen_values = true,
+            trailing_var_arg = true
+        )]
+        args: Vec<String>,
+    },
+}
+
 #[derive(Clone, Debug, Subcommand)]
 pub enum EditJobHookCommands {
     #[command(
@@ -272,6 +551,22 @@ pub enum EditJobHookCommands {
         #[arg(help = "Hook name to delete")]
         hook: String,
     },
+    #[command(
+        about = "Stop a hook of the job's template from applying to the job",
+        override_usage = "tend edit <NAME> hook disable <HOOK>"
+    )]
+    Disable {
+        #[arg(help = "Template hook name to disable")]
+        hook: String,
+    },
+    #[command(
+        about = "Apply a disabled hook of the job's template to the job again",
+        override_usage = "tend edit <NAME> hook enable <HOOK>"
+    )]
+    Enable {
+        #[arg(help = "Template hook name to enable")]
+        hook: String,
+    },
 }
 
 #[derive(Clone, Debug, Subcommand)]
@@ -289,3 +584,240 @@ pub enum JobHook {
         stream: crate::job::event::Stream,
     },
 }
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct RunOptions {
+    #[arg(long, default_value = "text", help = "How to print output and events")]
+    pub output: OutputMode,
+    #[arg(
+        long,
+        help = "Stop all jobs as soon as one of them fails and will not be restarted"
+    )]
+    pub fail_fast: bool,
+    #[arg(
+        long,
+        value_name = "JOB",
+        num_args = 1..,
+        use_value_delimiter = true,
+        help = "Stop all jobs as soon as the given job(s) stop"
+    )]
+    pub exit_on: Vec<String>,
+    #[arg(
+        long,
+        conflicts_with = "restart",
+        help = "Run jobs once: do not restart them after they exit or when a hook asks to"
+    )]
+    pub once: bool,
+    #[arg(
+        long,
+        help = "Override when jobs restart after they exit, without saving it"
+    )]
+    pub restart: Option<RestartBehavior>,
+    #[arg(
+        long,
+        help = "Override how long to wait between restarts, without saving it"
+    )]
+    pub restart_strategy: Option<RestartStrategy>,
+    #[command(flatten)]
+    pub format: OutputOptions,
+    #[command(flatten)]
+    pub sources: JobSources,
+}
+
+/// Chooses between saved jobs and the jobs of the project file (`tend.toml`).
+#[derive(Debug, Clone, Copy, Default, clap::Args)]
+pub struct JobSources {
+    #[arg(
+        long,
+        conflicts_with = "global",
+        help = "Only use jobs from the nearest tend.toml project file"
+    )]
+    pub project: bool,
+    #[arg(
+        long,
+        help = "Only use saved jobs, ignoring any tend.toml project file"
+    )]
+    pub global: bool,
+}
+
+impl From<JobSources> for Sources {
+    fn from(sources: JobSources) -> Self {
+        if sources.project {
+            Self::Project
+        } else if sources.global {
+            Self::Global
+        } else {
+            Self::All
+        }
+    }
+}
+
+/// Options describing a job, shared by `tend create` and `tend exec`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct JobArgs {
+    #[arg(help = "Executable to run [default: the program of the template]")]
+    pub program: Option<String>,
+    #[arg(
+        long,
+        short = 'r',
+        help = "When to restart the job after it exits [default: always, or set by the template]"
+    )]
+    pub restart: Option<RestartBehavior>,
+    #[arg(
+        long,
+        help = "How long to wait between automatic restarts [default: exponential-backoff, or set by the template]"
+    )]
+    pub restart_strategy: Option<RestartStrategy>,
+    #[arg(
+        long,
+        short = 'g',
+        help = "Assign the job to a group",
+        default_value = "default"
+    )]
+    pub group: String,
+    #[arg(
+        long,
+        short = 't',
+        value_parser = crate::job::name::parse_template,
+        help = "Apply a built-in or user-defined job template"
+    )]
+    pub template: Option<String>,
+    #[arg(
+        long,
+        value_name = "NAME=VALUE",
+        value_parser = parse_param,
+        requires = "template",
+        help = "Set a parameter of the template"
+    )]
+    pub param: Vec<(String, String)>,
+    #[arg(
+        long,
+        short = 'e',
+        value_name = "KEY=VALUE",
+        value_parser = parse_env,
+        help = "Set an environment variable for the program"
+    )]
+    pub env: Vec<(String, String)>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Restart the job when its output contains the text"
+    )]
+    pub restart_on: Vec<String>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Restart the job with the short backoff when its output contains the text"
+    )]
+    pub fast_restart_on: Vec<String>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Stop the job when its output contains the text"
+    )]
+    pub stop_on: Vec<String>,
+    #[arg(
+        long,
+        help = "Truncate output lines longer than this many bytes [default: 65536]"
+    )]
+    pub max_line_length: Option<usize>,
+    #[command(flatten)]
+    pub output: OutputOptions,
+    #[arg(
+        long,
+        help = "Color of the job name in output [default: based on the name]"
+    )]
+    pub color: Option<JobColor>,
+    #[arg(
+        help = "Arguments passed to the program; use -- before program args that begin with a dash"
+    )]
+    pub args: Vec<String>,
+}
+
+impl JobArgs {
+    pub fn into_job(self, name: String) -> anyhow::Result<Job> {
+        let hooks = [
+            ("restart-on", Action::Restart, self.restart_on),
+            ("fast-restart-on", Action::FastRestart, self.fast_restart_on),
+            ("stop-on", Action::Stop, self.stop_on),
+        ];
+        let event_hooks = hooks
+            .into_iter()
+            .flat_map(|(prefix, action, texts)| {
+                texts
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, contains)| Hook {
+                        name: format!("{prefix}-{}", i + 1),
+                        event: Event::DetectSubstring {
+                            stream: Stream::Any,
+                            contains,
+                        },
+                        action: action.clone(),
+                    })
+            })
+            .collect();
+
+        let mut job = Job {
+            version: crate::job::migrate::CURRENT_VERSION,
+            name,
+            enabled: true,
+            program: String::new(),
+            args: vec![],
+            restart: RestartBehavior::default(),
+            group: self.group,
+            working_directory: std::env::current_dir()?,
+            restart_strategy: RestartStrategy::default(),
+            event_hooks,
+            env: self.env.into_iter().collect(),
+            template: self.template,
+            template_params: self.param.into_iter().collect(),
+            disabled_template_hooks: vec![],
+            max_line_length: None,
+            output: self.output,
+            color: self.color,
+        };
+
+        // Options given on the command line take precedence over the template.
+        let template = job.load_template()?.unwrap_or_default();
+        job.apply_template_settings(&template);
+        match (self.program, template.program) {
+            (Some(program), _) => {
+                job.program = program;
+                job.args = self.args;
+            }
+            (None, Some(program)) => {
+                job.program = program;
+                job.args = template.args;
+            }
+            (None, None) => anyhow::bail!("A program to run is required."),
+        }
+        if let Some(restart) = self.restart {
+            job.restart = restart;
+        }
+        if let Some(restart_strategy) = self.restart_strategy {
+            job.restart_strategy = restart_strategy;
+        }
+        if self.max_line_length.is_some() {
+            job.max_line_length = self.max_line_length;
+        }
+
+        Ok(job)
+    }
+}
+
+/// Parses a `NAME=VALUE` template parameter.
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    match param.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{param}'")),
+    }
+}
+
+/// Parses a `KEY=VALUE` environment variable.
+fn parse_env(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{var}'")),
+    }
+}
@@ -1,8 +1,10 @@
-use colored::{ColoredString, Colorize};
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
 
 pub trait Tend {
     fn thick(&self) -> ColoredString;
     fn job(&self) -> ColoredString;
+    fn job_with(&self, color: JobColor) -> ColoredString;
     // fn program(&self) -> ColoredString;
     fn time_value(&self) -> ColoredString;
     fn success(&self) -> ColoredString;
@@ -18,6 +20,10 @@ impl Tend for str {
         self.bold().cyan()
     }
 
+    fn job_with(&self, color: JobColor) -> ColoredString {
+        self.bold().color(Color::from(color))
+    }
+
     // fn program(&self) -> ColoredString { self.bold().yellow() }
 
     fn time_value(&self) -> ColoredString {
@@ -32,3 +38,124 @@ impl Tend for str {
         self.bold().red()
     }
 }
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, clap::ValueEnum, PartialEq, Eq,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl From<JobColor> for Color {
+    fn from(color: JobColor) -> Self {
+        match color {
+            JobColor::Red => Self::Red,
+            JobColor::Green => Self::Green,
+            JobColor::Yellow => Self::Yellow,
+            JobColor::Blue => Self::Blue,
+            JobColor::Magenta => Self::Magenta,
+            JobColor::Cyan => Self::Cyan,
+            JobColor::White => Self::White,
+            JobColor::BrightRed => Self::BrightRed,
+            JobColor::BrightGreen => Self::BrightGreen,
+            JobColor::BrightYellow => Self::BrightYellow,
+            JobColor::BrightBlue => Self::BrightBlue,
+            JobColor::BrightMagenta => Self::BrightMagenta,
+            JobColor::BrightCyan => Self::BrightCyan,
+            JobColor::BrightWhite => Self::BrightWhite,
+        }
+    }
+}
+
+/// Colors of job names and streams in the output of running jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorScheme {
+    /// Colors assigned to jobs without a color of their own.
+    pub jobs: Vec<JobColor>,
+    /// Color of the marker on lines printed to stderr.
+    pub stderr: JobColor,
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        // Red is left out so that it stays reserved for stderr and failures.
+        Self {
+            jobs: vec![
+                JobColor::Cyan,
+                JobColor::Green,
+                JobColor::Yellow,
+                JobColor::Blue,
+                JobColor::Magenta,
+                JobColor::BrightCyan,
+                JobColor::BrightGreen,
+                JobColor::BrightYellow,
+                JobColor::BrightBlue,
+                JobColor::BrightMagenta,
+            ],
+            stderr: JobColor::Red,
+        }
+    }
+}
+
+impl ColorScheme {
+    /// Assigns a color to each job, keeping the color a job was configured with.
+    ///
+    /// Other jobs get a color derived from a hash of their name so that a job keeps its color
+    /// between runs. Jobs whose color is already taken move on to the next free color while there
+    /// are any, so jobs running together are told apart. `jobs` should be sorted to keep this
+    /// stable.
+    pub fn assign(&self, jobs: &[(&str, Option<JobColor>)]) -> Vec<JobColor> {
+        let mut used: Vec<bool> = self
+            .jobs
+            .iter()
+            .map(|color| {
+                jobs.iter()
+                    .any(|(_, configured)| *configured == Some(*color))
+            })
+            .collect();
+
+        jobs.iter()
+            .map(|&(name, configured)| {
+                if let Some(color) = configured {
+                    return color;
+                }
+                if self.jobs.is_empty() {
+                    return JobColor::Cyan;
+                }
+
+                let start = usize::try_from(fnv1a(name) % self.jobs.len() as u64).unwrap_or(0);
+                let index = (0..self.jobs.len())
+                    .map(|offset| (start + offset) % self.jobs.len())
+                    .find(|&index| !used.get(index).copied().unwrap_or(true))
+                    .unwrap_or(start);
+
+                if let Some(used) = used.get_mut(index) {
+                    *used = true;
+                }
+                self.jobs.get(index).copied().unwrap_or(JobColor::Cyan)
+            })
+            .collect()
+    }
+}
+
+/// Hash that does not change between Rust versions, unlike [`std::hash::DefaultHasher`].
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,45 @@
+use crate::{colors::ColorScheme, job::format::Format};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// User settings stored in `config.json` in the tend home directory.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub colors: ColorScheme,
+    /// Format of newly saved jobs.
+    pub job_format: Format,
+    /// More directories to load jobs from, e.g. one shared by a team.
+    ///
+    /// Relative paths are relative to the tend home directory.
+    pub stores: Vec<PathBuf>,
+}
+
+/// The tend home directory: `$TEND_HOME`, or `~/.tend` by default.
+pub fn home() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os("TEND_HOME").filter(|home| !home.is_empty()) {
+        return Ok(PathBuf::from(home));
+    }
+
+    let home =
+        dirs_next::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home.join(".tend"))
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf> {
+        Ok(home()?.join("config.json"))
+    }
+
+    /// Loads the configuration, or the defaults if there is no configuration file.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        match std::fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)
+                .with_context(|| format!("Invalid configuration file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
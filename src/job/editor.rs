@@ -0,0 +1,121 @@
+use super::{Job, format::Format};
+use crate::colors::Tend;
+use anyhow::Result;
+use std::io::Write;
+
+impl Job {
+    /// Opens the saved job in the user's editor and saves it back once it parses and validates.
+    ///
+    /// The job is edited in a temporary copy, so the saved job is never left broken. Returns
+    /// `false` if the user gave up without saving.
+    pub fn open_in_editor(name: &str) -> Result<bool> {
+        let Some((path, format)) = Self::find_file(name)? else {
+            anyhow::bail!("Job '{name}' was not found.");
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Job '{name}' could not be read: {e}"))?;
+
+        let temp = std::env::temp_dir().join(format!(
+            "tend-{}-{name}.{}",
+            std::process::id(),
+            format.extension()
+        ));
+        std::fs::write(&temp, &contents)?;
+
+        let Some(edited) = Self::edit_until_valid(name, &temp, format).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })?
+        else {
+            let _ = std::fs::remove_file(&temp);
+            return Ok(false);
+        };
+
+        let store = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+        let _lock = Self::lock_store(store)?;
+        // Another command may have changed the job while it was open in the editor.
+        if std::fs::read_to_string(&path).ok().as_ref() != Some(&contents) {
+            anyhow::bail!(
+                "Job '{name}' was changed by another process while it was being edited. Your changes were not saved and are kept in {}",
+                temp.display()
+            );
+        }
+
+        // Save the file as written, keeping comments and formatting.
+        super::io::write_atomic(&path, edited.as_bytes(), true)?;
+        let _ = std::fs::remove_file(&temp);
+        Ok(true)
+    }
+
+    fn edit_until_valid(
+        name: &str,
+        temp: &std::path::Path,
+        format: Format,
+    ) -> Result<Option<String>> {
+        loop {
+            Self::run_editor(temp)?;
+
+            let contents = std::fs::read_to_string(temp)?;
+            let error = match Self::parse(format, &contents) {
+                Ok(job) if job.name != name => {
+                    format!("the job name must stay '{name}'; use tend rename to rename it")
+                }
+                Ok(job) => match job.validate() {
+                    Ok(()) => return Ok(Some(contents)),
+                    Err(e) => e.to_string(),
+                },
+                Err(e) => e.to_string(),
+            };
+
+            eprintln!("{} {}: {error}", name.job(), "is not valid".failure());
+            if !Self::confirm("Edit again? [Y/n] ")? {
+                eprintln!("Discarded changes to {}", name.job());
+                return Ok(None);
+            }
+        }
+    }
+
+    fn run_editor(path: &std::path::Path) -> Result<()> {
+        let editor = std::env::var("VISUAL")
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .or_else(|| std::env::var("EDITOR").ok())
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| {
+                if cfg!(windows) {
+                    "notepad".to_string()
+                } else {
+                    "vi".to_string()
+                }
+            });
+
+        // Editors are often configured with arguments, e.g. `code --wait`.
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().unwrap_or_default();
+        let status = std::process::Command::new(program)
+            .args(parts)
+            .arg(path)
+            .status()
+            .map_err(|e| anyhow::anyhow!("Could not start editor '{editor}': {e}"))?;
+
+        if !status.success() {
+            anyhow::bail!("Editor '{editor}' exited with {status}");
+        }
+
+        Ok(())
+    }
+
+    fn confirm(prompt: &str) -> Result<bool> {
+        eprint!("{prompt}");
+        std::io::stderr().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(false);
+        }
+
+        Ok(matches!(
+            answer.trim().to_lowercase().as_str(),
+            "" | "y" | "yes"
+        ))
+    }
+}
@@ -1,18 +1,29 @@
-use crate::colors::Tend;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
-use super::Job;
+use super::{Job, outcome::StopReason};
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ControlFlow<'a> {
     Nothing,
     FastRestartCommand(&'a str),
     RestartCommand(&'a str),
     StopJob(&'a str),
+    Finished(StopReason),
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, clap::ValueEnum, Copy, PartialEq, Eq)]
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    clap::ValueEnum,
+    Copy,
+    PartialEq,
+    Eq,
+)]
 pub enum RestartBehavior {
     #[default]
     Always,
@@ -21,7 +32,18 @@ pub enum RestartBehavior {
     Never,
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, clap::ValueEnum, Copy, PartialEq, Eq)]
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    clap::ValueEnum,
+    Copy,
+    PartialEq,
+    Eq,
+)]
 pub enum RestartStrategy {
     Immediate,
     #[default]
@@ -50,7 +72,18 @@ impl RestartStrategy {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ValueEnum, Default)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    ValueEnum,
+    Default,
+    PartialEq,
+    Eq,
+)]
 pub enum Stream {
     Stdout,
     Stderr,
@@ -59,29 +92,51 @@ pub enum Stream {
 }
 
 /// TODO: Rework [`Job::restart`] to use this instead of [`JobRestartStrategy`]
-#[derive(Debug, Clone, Serialize, Deserialize, clap::Parser)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, clap::Parser)]
+#[schemars(description = "What a hook reacts to.")]
 pub enum Event {
     // FinishedSuccess,
     // FinishedFailure,
     DetectSubstring { stream: Stream, contains: String },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ValueEnum)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, ValueEnum)]
 pub enum Action {
     Restart,
     FastRestart,
     Stop,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Hook {
     pub name: String,
     pub event: Event,
     pub action: Action,
 }
 
+impl std::fmt::Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Event::DetectSubstring { stream, contains } = &self.event;
+        let stream = match stream {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+            Stream::Any => "stdout or stderr",
+        };
+        let action = match self.action {
+            Action::Restart => "restart the job",
+            Action::FastRestart => "restart the job with the short backoff",
+            Action::Stop => "stop the job",
+        };
+        write!(
+            f,
+            "{}: when {stream} contains {contains:?}, {action}",
+            self.name
+        )
+    }
+}
+
 impl Job {
-    pub fn stdout_line_callback<'a>(&'a self, line: &str, verbose: bool) -> ControlFlow<'a> {
+    pub fn stdout_line_callback<'a>(&'a self, line: &str) -> ControlFlow<'a> {
         for hook in &self.event_hooks {
             let Hook {
                 name,
@@ -95,10 +150,6 @@ impl Job {
             };
 
             if detection {
-                if verbose {
-                    println!("{} triggered hook {:?}", self.name.job(), hook);
-                }
-
                 return match action {
                     Action::Restart => ControlFlow::RestartCommand(name),
                     Action::FastRestart => ControlFlow::FastRestartCommand(name),
@@ -110,7 +161,7 @@ impl Job {
         ControlFlow::Nothing
     }
 
-    pub fn stderr_line_callback<'a>(&'a self, line: &str, verbose: bool) -> ControlFlow<'a> {
+    pub fn stderr_line_callback<'a>(&'a self, line: &str) -> ControlFlow<'a> {
         for hook in &self.event_hooks {
             let Hook {
                 name,
@@ -124,10 +175,6 @@ impl Job {
             };
 
             if detection {
-                if verbose {
-                    println!("{} triggered hook {:?}", self.name.job(), hook);
-                }
-
                 return match action {
                     Action::Restart => ControlFlow::RestartCommand(name),
                     Action::FastRestart => ControlFlow::FastRestartCommand(name),
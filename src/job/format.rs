@@ -0,0 +1,131 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// File format of a saved job, told apart by the file extension.
+///
+/// JSON jobs are saved without an extension, as they always have been.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Extensions recognized for each format.
+    const fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Self::Json => &["json"],
+            Self::Toml => &["toml"],
+            Self::Yaml => &["yaml", "yml"],
+        }
+    }
+
+    /// Usual extension of files in this format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    /// Name of the file a job with the given name is saved to in this format.
+    pub fn file_name(self, name: &str) -> String {
+        match self {
+            Self::Json => name.to_string(),
+            Self::Toml | Self::Yaml => format!("{name}.{}", self.extension()),
+        }
+    }
+
+    /// All file names a job with the given name may be saved under, with their formats.
+    pub fn candidates(name: &str) -> Vec<(String, Self)> {
+        let mut candidates = vec![(name.to_string(), Self::Json)];
+        for format in Self::value_variants() {
+            for extension in format.extensions() {
+                candidates.push((format!("{name}.{extension}"), *format));
+            }
+        }
+        candidates
+    }
+
+    /// Splits a file name from the jobs directory into the job name and format.
+    pub fn from_file_name(file_name: &str) -> (&str, Self) {
+        for format in Self::value_variants() {
+            for extension in format.extensions() {
+                if let Some(name) = file_name
+                    .strip_suffix(extension)
+                    .and_then(|name| name.strip_suffix('.'))
+                {
+                    return (name, *format);
+                }
+            }
+        }
+        (file_name, Self::Json)
+    }
+
+    /// Whether a name ends with an extension of one of the formats.
+    pub fn has_extension(name: &str) -> bool {
+        Self::from_file_name(name).0 != name
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Toml => toml::from_str(contents)?,
+            Self::Yaml => serde_yaml_ng::from_str(contents)?,
+        })
+    }
+
+    /// Parses a document into a JSON value, e.g. to upgrade it before deserializing it.
+    ///
+    /// YAML writes enum variants as tags such as `!DetectSubstring`, which JSON values cannot
+    /// hold, so they are turned into maps with the variant name as the only key, as in JSON.
+    pub fn parse_value(self, contents: &str) -> Result<serde_json::Value> {
+        match self {
+            Self::Json | Self::Toml => self.parse(contents),
+            Self::Yaml => Ok(serde_json::to_value(untag(serde_yaml_ng::from_str(
+                contents,
+            )?))?),
+        }
+    }
+
+    /// Parses a document through [`Format::parse_value`], so that YAML accepts enum variants
+    /// both as tags and as single-key maps, the way JSON and TOML write them.
+    pub fn parse_document<T: DeserializeOwned>(self, contents: &str) -> Result<T> {
+        Ok(serde_json::from_value(self.parse_value(contents)?)?)
+    }
+
+    pub fn to_string_pretty<T: Serialize>(self, value: &T) -> Result<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(value)?,
+            Self::Toml => toml::to_string_pretty(value)?,
+            Self::Yaml => serde_yaml_ng::to_string(value)?,
+        })
+    }
+}
+
+/// Replaces YAML tags with single-key maps, e.g. `!Stop {}` with `{Stop: {}}`.
+fn untag(value: serde_yaml_ng::Value) -> serde_yaml_ng::Value {
+    use serde_yaml_ng::Value;
+
+    match value {
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let variant = tag.strip_prefix('!').unwrap_or(&tag).to_string();
+            let mut map = serde_yaml_ng::Mapping::new();
+            map.insert(Value::String(variant), untag(tagged.value));
+            Value::Mapping(map)
+        }
+        Value::Sequence(values) => Value::Sequence(values.into_iter().map(untag).collect()),
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .map(|(key, value)| (untag(key), untag(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
@@ -1,7 +1,14 @@
-use super::{Job, filter};
-use crate::colors::Tend;
+use super::{Job, filter, format::Format, name, project::Sources};
+use crate::{
+    colors::Tend,
+    config::{self, Config},
+};
 use anyhow::Result;
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 use tabled::{
     builder::Builder,
     settings::{
@@ -10,68 +17,372 @@ use tabled::{
     },
 };
 
+/// Lock file taken in a store while jobs in it are changed. Not a valid job name.
+const LOCK_FILE: &str = ".lock";
+
+/// Jobs directory given with `--jobs-dir`, used instead of the one in the tend home directory.
+static JOBS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 impl Job {
+    /// Uses the given directory to save jobs instead of the one in the tend home directory.
+    pub fn set_jobs_dir(dir: PathBuf) -> Result<()> {
+        let dir = std::env::current_dir()?.join(dir);
+        JOBS_DIR.set(dir).map_err(|dir| {
+            anyhow::anyhow!("Jobs directory is already set, not using {}", dir.display())
+        })
+    }
+
+    /// Directory new jobs are saved to.
     fn jobs_dir() -> Result<PathBuf> {
-        let home = dirs_next::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        let jobs = home.join(".tend").join("jobs");
+        let jobs = match JOBS_DIR.get() {
+            Some(dir) => dir.clone(),
+            None => config::home()?.join("jobs"),
+        };
         std::fs::create_dir_all(&jobs)?;
         Ok(jobs)
     }
 
-    pub fn save(&self, overwrite: bool) -> Result<()> {
-        let jobs = Self::jobs_dir()?;
+    /// Directories jobs are loaded from: the jobs directory, then the configured stores.
+    ///
+    /// When several stores have a job with the same name, the first one wins. With
+    /// `--jobs-dir`, only that directory is used so that job setups can be tried in isolation.
+    fn stores() -> Result<Vec<PathBuf>> {
+        let mut stores = vec![Self::jobs_dir()?];
+        if JOBS_DIR.get().is_some() {
+            return Ok(stores);
+        }
+        for store in Config::load()?.stores {
+            let store = config::home()?.join(store);
+            if !stores.contains(&store) {
+                stores.push(store);
+            }
+        }
+        Ok(stores)
+    }
+
+    /// Finds the file a job is saved in, along with its format.
+    ///
+    /// Fails for invalid names, so the path is always inside one of the stores.
+    pub fn find_file(name: &str) -> Result<Option<(PathBuf, Format)>> {
+        name::validate(name)?;
+        for store in Self::stores()? {
+            if let Some(file) = Self::find_file_in(&store, name) {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_file_in(store: &Path, name: &str) -> Option<(PathBuf, Format)> {
+        Format::candidates(name)
+            .into_iter()
+            .map(|(file_name, format)| (store.join(file_name), format))
+            .find(|(path, _)| path.is_file())
+    }
+
+    /// Finds the file of a job saved before job names were validated, such as `my job`.
+    ///
+    /// Only accepts names of plain files directly inside one of the stores, so that such jobs
+    /// can be renamed to a valid name.
+    fn find_invalid_file(name: &str) -> Result<Option<(PathBuf, Format)>> {
+        if !is_plain_file_name(name) {
+            return Ok(None);
+        }
+        for store in Self::stores()? {
+            if let Some(file) = Self::find_file_in(&store, name) {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Path of the file a job with the given name is saved to, or would be saved to.
+    pub fn path(name: &str) -> Result<PathBuf> {
+        match Self::find_file(name)? {
+            Some((path, _)) => Ok(path),
+            None => Ok(Self::jobs_dir()?.join(Config::load()?.job_format.file_name(name))),
+        }
+    }
+
+    /// Names of the jobs saved in the stores, skipping files that are not jobs.
+    ///
+    /// Jobs with the same name in several stores are reported and listed once.
+    fn job_file_names() -> Result<Vec<String>> {
+        let mut names: Vec<(String, PathBuf)> = vec![];
+        for store in Self::stores()? {
+            let store_names = match Self::store_job_names(&store) {
+                Ok(store_names) => store_names,
+                Err(e) => {
+                    eprintln!(
+                        "{} {}: {e}",
+                        "Could not read job store".failure(),
+                        store.display()
+                    );
+                    continue;
+                }
+            };
+
+            for name in store_names {
+                if let Some((_, first)) = names.iter().find(|(n, _)| *n == name) {
+                    eprintln!(
+                        "{} is defined in both {} and {}; using the one in {}",
+                        name.job(),
+                        first.display(),
+                        store.display(),
+                        first.display()
+                    );
+                } else {
+                    names.push((name, store.clone()));
+                }
+            }
+        }
+
+        Ok(names.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Names of the jobs saved in one store.
+    fn store_job_names(store: &Path) -> std::io::Result<Vec<String>> {
+        let mut names: Vec<String> = vec![];
+        for entry in std::fs::read_dir(store)? {
+            let entry = entry?;
+            // Follow symlinks, as looking jobs up by name does.
+            if !entry.path().is_file() {
+                continue;
+            }
+            if let Ok(file_name) = entry.file_name().into_string() {
+                let (name, _) = Format::from_file_name(&file_name);
+                if name::validate(name).is_ok() && !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Files in the stores that look like jobs but whose names are not valid job names,
+    /// with the reason each name is invalid.
+    fn invalid_job_files() -> Result<Vec<(PathBuf, String)>> {
+        let mut files = vec![];
+        for store in Self::stores()? {
+            let Ok(entries) = std::fs::read_dir(&store) else {
+                continue;
+            };
+            for entry in entries {
+                let entry = entry?;
+                if !entry.path().is_file() {
+                    continue;
+                }
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                // Lock and temporary files are hidden.
+                if file_name.starts_with('.') {
+                    continue;
+                }
+                let (name, _) = Format::from_file_name(&file_name);
+                if let Err(e) = name::validate(name) {
+                    files.push((entry.path(), e.to_string()));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Store a job is saved in and its format, or where a new job would be saved.
+    fn location(name: &str) -> Result<(PathBuf, Option<Format>)> {
+        Ok(match Self::find_file(name)? {
+            Some((path, format)) => (parent(&path).to_path_buf(), Some(format)),
+            None => (Self::jobs_dir()?, None),
+        })
+    }
+
+    /// Takes an exclusive advisory lock on a store, released when the file is dropped.
+    ///
+    /// Held while reading, changing and writing jobs so that concurrent `tend` commands do
+    /// not lose each other's changes.
+    pub fn lock_store(store: &Path) -> Result<std::fs::File> {
         let file = std::fs::OpenOptions::new()
-            .write(true)
             .create(true)
-            .truncate(overwrite)
-            .create_new(!overwrite)
-            .open(jobs.join(&self.name))?;
-        // serde_json::to_writer(file, self)?;
-        serde_json::to_writer_pretty(file, self)?;
+            .truncate(false)
+            .write(true)
+            .open(store.join(LOCK_FILE))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Saves the job in the format it is already saved in, or the configured default format.
+    pub fn save(&self, overwrite: bool) -> Result<()> {
+        let (store, format) = Self::location(&self.name)?;
+        let format = match format {
+            Some(format) => format,
+            None => Config::load()?.job_format,
+        };
+        let _lock = Self::lock_store(&store)?;
+        self.write(&store, format, overwrite)
+    }
+
+    /// Saves the job in the given format, removing files of the job in other formats.
+    pub fn save_as(&self, format: Format, overwrite: bool) -> Result<()> {
+        let (store, _) = Self::location(&self.name)?;
+        let _lock = Self::lock_store(&store)?;
+        self.write(&store, format, overwrite)
+    }
+
+    /// Loads a job, lets `f` change it and saves it, holding the store lock throughout.
+    pub fn update<F>(name: &str, verbose: bool, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let (store, _) = Self::location(name)?;
+        let _lock = Self::lock_store(&store)?;
+
+        // Look the job up again now that no other `tend` command can move it.
+        let Some((_, format)) = Self::find_file_in(&store, name) else {
+            anyhow::bail!("Job '{name}' was not found or could not be loaded.");
+        };
+        let mut job = Self::load(name, verbose)
+            .ok_or_else(|| anyhow::anyhow!("Job '{name}' was not found or could not be loaded."))?;
+        f(&mut job)?;
+        job.write(&store, format, true)
+    }
+
+    fn write(&self, store: &Path, format: Format, overwrite: bool) -> Result<()> {
+        if !overwrite && Self::find_file(&self.name)?.is_some() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists).into());
+        }
+
+        let path = store.join(format.file_name(&self.name));
+        write_atomic(&path, format.to_string_pretty(self)?.as_bytes(), overwrite)?;
+
+        for (file_name, _) in Format::candidates(&self.name) {
+            let other = store.join(file_name);
+            if other != path && other.is_file() {
+                std::fs::remove_file(other)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Saves a job under a new name in the same store, then removes the file saved under the
+    /// old one.
+    ///
+    /// The old name may be one that is no longer valid, so that such jobs can be recovered.
+    pub fn rename(name: &str, new_name: String, overwrite: bool, verbose: bool) -> Result<()> {
+        if new_name == name {
+            anyhow::bail!("Job is already named '{new_name}'.");
+        }
+
+        let not_found = || anyhow::anyhow!("Job '{name}' was not found or could not be loaded.");
+        let (old_path, _) = match name::validate(name) {
+            Ok(()) => Self::find_file(name)?,
+            Err(e) => Some(Self::find_invalid_file(name)?.ok_or(e)?),
+        }
+        .ok_or_else(not_found)?;
+        let store = parent(&old_path).to_path_buf();
+        let _lock = Self::lock_store(&store)?;
+
+        // Load the job only now that no other `tend` command can change it.
+        let (old_path, format) = Self::find_file_in(&store, name).ok_or_else(not_found)?;
+        let mut job = Self::read(name, &old_path, format, verbose).ok_or_else(not_found)?;
+        job.name = new_name;
+        let replaced = Self::find_file(&job.name)?.is_some();
+        job.write(&store, format, overwrite)?;
+        if let Err(e) = std::fs::remove_file(&old_path) {
+            // Do not leave the job saved under both names, unless that would delete the job
+            // it replaced.
+            if !replaced {
+                let _ = std::fs::remove_file(store.join(format.file_name(&job.name)));
+            }
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    /// Saves a copy of a job under a new name after letting `f` change it, holding the locks
+    /// of the stores of both jobs throughout.
+    pub fn copy<F>(name: &str, new_name: &str, overwrite: bool, verbose: bool, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        let not_found = || anyhow::anyhow!("Job '{name}' was not found or could not be loaded.");
+        let (path, _) = Self::find_file(name)?.ok_or_else(not_found)?;
+        let (store, format) = Self::location(new_name)?;
+        let mut stores = vec![parent(&path).to_path_buf(), store.clone()];
+        // Always lock stores in the same order so that two copies cannot wait on each other.
+        stores.sort();
+        stores.dedup();
+        let _locks = stores
+            .iter()
+            .map(|store| Self::lock_store(store))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut job = Self::load(name, verbose).ok_or_else(not_found)?;
+        job.name = new_name.to_string();
+        f(&mut job)?;
+        let format = match format {
+            Some(format) => format,
+            None => Config::load()?.job_format,
+        };
+        job.write(&store, format, overwrite)
+    }
+
     pub fn load(name: &str, verbose: bool) -> Option<Self> {
-        let jobs = Self::jobs_dir().ok()?;
-        let file = std::fs::File::open(jobs.join(name)).ok()?;
+        let (path, format) = match Self::find_file(name) {
+            Ok(Some(file)) => file,
+            Ok(None) => return None,
+            Err(e) => {
+                eprintln!("{} {}: {}", name.job(), "could not be loaded".failure(), e);
+                return None;
+            }
+        };
+        Self::read(name, &path, format, verbose)
+    }
+
+    /// Loads a job from the file it is saved in.
+    fn read(name: &str, path: &Path, format: Format, verbose: bool) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
 
-        let job: Result<Self, _> = serde_json::from_reader(file);
+        let job = Self::parse(format, &contents);
         match job {
             Ok(mut job) => {
-                if let Some(template) = job.template {
-                    job.apply_template(template);
-                }
+                // The file name decides where the job is saved back to.
+                job.name = name.to_string();
 
                 Some(job)
             }
             Err(e) => {
                 eprintln!("{} {}: {}", name.job(), "could not be loaded".failure(), e);
                 if verbose {
-                    eprintln!("Job file for {}: {}", name.job(), jobs.join(name).display());
+                    eprintln!("Job file for {}: {}", name.job(), path.display());
                 }
                 None
             }
         }
     }
 
+    /// Deletes all jobs in the jobs directory. Jobs in other stores are shared and kept.
     pub fn delete_all_unchecked() -> Result<()> {
-        let jobs = Self::jobs_dir()?;
-        for entry in std::fs::read_dir(jobs)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                std::fs::remove_file(path)?;
-            }
+        for name in Self::store_job_names(&Self::jobs_dir()?)? {
+            Self::delete_unchecked(&name)?;
         }
 
         Ok(())
     }
 
+    /// Deletes a job from the jobs directory, refusing to delete jobs of other stores.
     pub fn delete_unchecked(name: &str) -> Result<()> {
-        let jobs = Self::jobs_dir()?;
-        std::fs::remove_file(jobs.join(name))?;
+        let Some((path, _)) = Self::find_file(name)? else {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        };
+        let store = parent(&path);
+        if store != Self::jobs_dir()? {
+            anyhow::bail!(
+                "'{name}' is in the shared store {}; only jobs in {} are deleted",
+                store.display(),
+                Self::jobs_dir()?.display()
+            );
+        }
+        std::fs::remove_file(path)?;
 
         Ok(())
     }
@@ -88,17 +399,9 @@ impl Job {
     where
         F: FnMut(&str),
     {
-        let jobs = Self::jobs_dir()?;
-        for entry in std::fs::read_dir(jobs)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let Some(job_name) = path.file_name().unwrap().to_str() else {
-                    continue;
-                };
-                if filter.matches_name(job_name) {
-                    f(job_name);
-                }
+        for job_name in Self::job_file_names()? {
+            if filter.matches_name(&job_name) {
+                f(&job_name);
             }
         }
 
@@ -114,33 +417,31 @@ impl Job {
     where
         F: FnMut(Self),
     {
-        let jobs = Self::jobs_dir()?;
-        for entry in std::fs::read_dir(jobs)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let name = path.file_name().unwrap().to_str().unwrap();
-                let Some(job) = Self::load(name, verbose) else {
-                    continue;
-                };
-
-                if !include_disabled && !job.enabled {
-                    continue;
-                }
+        for name in Self::job_file_names()? {
+            let Some(job) = Self::load(&name, verbose) else {
+                continue;
+            };
 
-                if !filter.matches(&job) {
-                    continue;
-                }
+            if !include_disabled && !job.enabled {
+                continue;
+            }
 
-                f(job);
+            if !filter.matches(&job) {
+                continue;
             }
+
+            f(job);
         }
 
         Ok(())
     }
 
-    pub fn list(job_filter: &filter::Filter, verbose: bool, no_color: bool) -> Result<()> {
-        let jobs = Self::jobs_dir()?;
+    pub fn list(
+        job_filter: &filter::Filter,
+        sources: Sources,
+        verbose: bool,
+        no_color: bool,
+    ) -> Result<()> {
         let mut builder = Builder::default();
         builder.push_record([
             "ENABLED",
@@ -152,31 +453,30 @@ impl Job {
             "GROUP",
         ]);
 
-        for entry in std::fs::read_dir(jobs)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let name = path.file_name().unwrap().to_str().unwrap();
-                let Some(job) = Self::load(name, verbose) else {
-                    continue;
-                };
-
-                if !job_filter.matches(&job) {
-                    continue;
-                }
+        if !matches!(sources, Sources::Project) {
+            for (path, reason) in Self::invalid_job_files()? {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let (name, _) = Format::from_file_name(&file_name);
+                eprintln!(
+                    "{} {}: {reason} Rename it with tend rename {name:?} <NEW_NAME>.",
+                    "Ignoring job file".failure(),
+                    path.display()
+                );
+            }
+        }
 
-                let enabled = if job.enabled { "*" } else { " " };
+        for job in Self::collect(job_filter, true, sources, verbose)? {
+            let enabled = if job.enabled { "*" } else { " " };
 
-                builder.push_record([
-                    enabled,
-                    &job.name,
-                    &job.program,
-                    &job.args.join(" "),
-                    &job.working_directory.display().to_string(),
-                    job.restart_behaviour(),
-                    &job.group,
-                ]);
-            }
+            builder.push_record([
+                enabled,
+                &job.name,
+                &job.program,
+                &job.args.join(" "),
+                &job.working_directory.display().to_string(),
+                job.restart_behaviour(),
+                &job.group,
+            ]);
         }
 
         let mut table = builder.build();
@@ -202,3 +502,75 @@ impl Job {
         Ok(())
     }
 }
+
+/// Directory containing a job file.
+fn parent(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new("."))
+}
+
+/// Whether a name is a single, visible file name, with no path separators or `..`.
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    ) && !name.contains(['/', '\\'])
+        && !name.starts_with('.')
+}
+
+/// Writes a file by writing a temporary file next to it and moving it into place, so readers
+/// see either the old or the new contents and a crash cannot leave a half-written file.
+///
+/// Without `overwrite`, fails with [`std::io::ErrorKind::AlreadyExists`] if the file exists.
+pub fn write_atomic(path: &Path, contents: &[u8], overwrite: bool) -> std::io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = parent(path).join(format!(".{file_name}.{}.tmp", std::process::id()));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        if overwrite {
+            std::fs::rename(&temp, path)
+        } else {
+            // Unlike a rename, a hard link does not replace an existing file.
+            std::fs::hard_link(&temp, path)
+        }
+    })();
+
+    let _ = std::fs::remove_file(&temp);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_file_names_are_single_normal_components() {
+        assert!(is_plain_file_name("my job"));
+        assert!(is_plain_file_name("web"));
+        for name in [
+            "",
+            ".",
+            "..",
+            "../x",
+            "a/b",
+            "a\\b",
+            "/etc/passwd",
+            ".hidden",
+            "x/",
+        ] {
+            assert!(!is_plain_file_name(name), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_names_outside_the_stores_are_not_found() {
+        for name in ["../x", "a/b", "..", ".hidden"] {
+            assert!(Job::find_invalid_file(name).unwrap().is_none(), "{name:?}");
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::Write;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Reads lines from a process output stream.
+///
+/// Unlike [`tokio::io::Lines`] this does not require valid UTF-8 (invalid bytes are replaced),
+/// keeps at most `max_length` bytes of a line in memory, and treats a lone `\r` as the end of a
+/// line so that progress output is not glued together into one huge line.
+#[derive(Debug)]
+pub struct OutputLines<R> {
+    reader: BufReader<R>,
+    line: Vec<u8>,
+    max_length: usize,
+    truncated: usize,
+    skip_newline: bool,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> OutputLines<R> {
+    pub fn new(inner: R, max_length: usize) -> Self {
+        Self {
+            reader: BufReader::new(inner),
+            line: Vec::new(),
+            max_length,
+            truncated: 0,
+            skip_newline: false,
+            done: false,
+        }
+    }
+
+    /// Returns `true` once the stream has reached end of file or failed.
+    pub const fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Stops reading the stream, e.g. when a grandchild keeps the pipe open.
+    pub const fn abandon(&mut self) {
+        self.done = true;
+    }
+
+    /// Returns the next line, or `None` at end of file.
+    ///
+    /// This method is cancel safe: partially read lines are kept and continued on the next call.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        loop {
+            let available = match self.reader.fill_buf().await {
+                Ok(available) => available,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            };
+
+            if available.is_empty() {
+                self.done = true;
+                if self.line.is_empty() && self.truncated == 0 {
+                    return Ok(None);
+                }
+                return Ok(Some(self.take_line()));
+            }
+
+            if self.skip_newline {
+                self.skip_newline = false;
+                if available.first() == Some(&b'\n') {
+                    self.reader.consume(1);
+                    continue;
+                }
+            }
+
+            if let Some(end) = available.iter().position(|&b| b == b'\n' || b == b'\r') {
+                let content = available.get(..end).unwrap_or_default();
+                append(
+                    &mut self.line,
+                    &mut self.truncated,
+                    self.max_length,
+                    content,
+                );
+                self.skip_newline = available.get(end) == Some(&b'\r');
+                self.reader.consume(end + 1);
+                return Ok(Some(self.take_line()));
+            }
+
+            let length = available.len();
+            append(
+                &mut self.line,
+                &mut self.truncated,
+                self.max_length,
+                available,
+            );
+            self.reader.consume(length);
+        }
+    }
+
+    fn take_line(&mut self) -> String {
+        let mut line = String::from_utf8_lossy(&self.line).into_owned();
+        if self.truncated != 0 {
+            let _ = write!(line, "... [{} bytes truncated]", self.truncated);
+        }
+        self.line.clear();
+        self.truncated = 0;
+        line
+    }
+}
+
+fn append(line: &mut Vec<u8>, truncated: &mut usize, max_length: usize, bytes: &[u8]) {
+    let room = max_length.saturating_sub(line.len()).min(bytes.len());
+    let (kept, dropped) = bytes.split_at(room);
+    line.extend_from_slice(kept);
+    *truncated += dropped.len();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    async fn lines(reader: impl AsyncRead + Unpin, max_length: usize) -> Vec<String> {
+        let mut output = OutputLines::new(reader, max_length);
+        let mut lines = vec![];
+        while let Some(line) = output.next_line().await.unwrap() {
+            lines.push(line);
+        }
+        assert!(output.is_done());
+        lines
+    }
+
+    #[tokio::test]
+    async fn replaces_invalid_utf8() {
+        assert_eq!(
+            lines(&b"ok \xff\xfe!\n"[..], 100).await,
+            ["ok \u{fffd}\u{fffd}!"]
+        );
+    }
+
+    #[tokio::test]
+    async fn truncates_long_lines() {
+        assert_eq!(
+            lines(&b"0123456789\nshort\n"[..], 4).await,
+            ["0123... [6 bytes truncated]", "shor... [1 bytes truncated]"]
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_progress_output_on_carriage_returns() {
+        assert_eq!(
+            lines(&b"10%\r50%\r100%\r\ndone\n"[..], 100).await,
+            ["10%", "50%", "100%", "done"]
+        );
+    }
+
+    #[tokio::test]
+    async fn joins_crlf_split_across_reads() {
+        let reader = (&b"first\r"[..]).chain(&b"\nsecond\r\n"[..]);
+        assert_eq!(lines(reader, 100).await, ["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn returns_last_line_without_newline() {
+        assert_eq!(lines(&b"one\ntwo"[..], 100).await, ["one", "two"]);
+        assert!(lines(&b""[..], 100).await.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Job, filter::Filter, format::Format, template::Template};
+use crate::colors::Tend;
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// Version of the job file schema written by this version of tend.
+///
+/// Bump it together with a new entry in [`MIGRATIONS`] whenever saved jobs need to change
+/// in a way `#[serde(default)]` cannot cover, such as renaming or restructuring a field.
+pub const CURRENT_VERSION: u32 = 3;
+
+/// Upgrades a job from the version before it to the one after it. Returns what it changed.
+type Migration = fn(&mut Map<String, Value>) -> Vec<String>;
+
+/// Migrations in order; the one at index `i` upgrades a job from version `i` to `i + 1`.
+const MIGRATIONS: &[Migration] = &[v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// Jobs saved before the schema had a version only need the version added.
+const fn v0_to_v1(_job: &mut Map<String, Value>) -> Vec<String> {
+    Vec::new()
+}
+
+/// Templates are referred to by name since user templates were added.
+fn v1_to_v2(job: &mut Map<String, Value>) -> Vec<String> {
+    match job.get("template").and_then(Value::as_str) {
+        Some("PortForward") => {
+            job.insert("template".to_string(), "port-forward".into());
+            vec!["refer to template PortForward by its name port-forward".to_string()]
+        }
+        _ => vec![],
+    }
+}
+
+/// Templates are applied when jobs run, so saved copies of their hooks are removed.
+///
+/// Hooks the user changed since they were copied are kept and replace the template's.
+fn v2_to_v3(job: &mut Map<String, Value>) -> Vec<String> {
+    let Some(Ok((template, _))) = job
+        .get("template")
+        .and_then(Value::as_str)
+        .map(Template::load)
+    else {
+        return vec![];
+    };
+    let Some(Value::Array(hooks)) = job.get_mut("event_hooks") else {
+        return vec![];
+    };
+
+    let mut changes = vec![];
+    hooks.retain(|hook| {
+        let copied = template
+            .event_hooks
+            .iter()
+            .any(|template_hook| serde_json::to_value(template_hook).ok().as_ref() == Some(hook));
+        if copied && let Some(name) = hook.get("name").and_then(Value::as_str) {
+            changes.push(format!("remove hook '{name}' copied from the template"));
+        }
+        !copied
+    });
+    changes
+}
+
+/// Upgrades a job definition to [`CURRENT_VERSION`]. Returns a description of each change.
+pub fn migrate(job: &mut Value) -> Result<Vec<String>> {
+    let Value::Object(job) = job else {
+        anyhow::bail!("a job must be a table or an object");
+    };
+
+    let version = match job.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("invalid version {version}"))?,
+    };
+    if version > CURRENT_VERSION {
+        anyhow::bail!(
+            "the job was saved with schema version {version}, but this version of tend only supports versions up to {CURRENT_VERSION}; upgrade tend to load it"
+        );
+    }
+
+    let mut changes = vec![];
+    for (from, migration) in MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(usize::try_from(version)?)
+    {
+        changes.extend(migration(job));
+        let to = u32::try_from(from)? + 1;
+        job.insert("version".to_string(), to.into());
+        changes.push(format!("upgrade schema version from {from} to {to}"));
+    }
+
+    Ok(changes)
+}
+
+impl Job {
+    /// Parses a job definition, upgrading it from older schema versions.
+    pub fn parse(format: Format, contents: &str) -> Result<Self> {
+        Self::parse_migrated(format, contents).map(|(job, _)| job)
+    }
+
+    /// Parses a job definition like [`Job::parse`], also returning what the upgrade changed.
+    pub fn parse_migrated(format: Format, contents: &str) -> Result<(Self, Vec<String>)> {
+        let mut value = format.parse_value(contents)?;
+        let changes = migrate(&mut value)?;
+        Ok((serde_json::from_value(value)?, changes))
+    }
+}
+
+impl Job {
+    /// Upgrades saved jobs matching the filter to [`CURRENT_VERSION`] and reports the changes.
+    ///
+    /// With `dry_run`, only reports what would change.
+    pub fn migrate_saved(filter: &Filter, dry_run: bool, verbose: bool) -> Result<()> {
+        let mut names = vec![];
+        Self::iterate_jobs_filtered(|job| names.push(job.name), filter, true, verbose)?;
+
+        let mut up_to_date = 0;
+        for name in names {
+            let Some((path, format)) = Self::find_file(&name)? else {
+                continue;
+            };
+            let (_, changes) = Self::parse_migrated(format, &std::fs::read_to_string(&path)?)?;
+            if changes.is_empty() {
+                up_to_date += 1;
+                if verbose {
+                    println!("{} is up to date", name.job());
+                }
+                continue;
+            }
+
+            if dry_run {
+                println!("Would migrate {} ({}):", name.job(), path.display());
+            } else {
+                Self::update(&name, verbose, |_| Ok(()))?;
+                println!("Migrated {} ({}):", name.job(), path.display());
+            }
+            for change in changes {
+                println!("  - {change}");
+            }
+        }
+
+        if up_to_date != 0 {
+            println!("{up_to_date} job(s) already up to date");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_with_hooks_round_trips_in_every_format() {
+        let job: Job = serde_json::from_value(serde_json::json!({
+            "version": CURRENT_VERSION,
+            "name": "web",
+            "group": "default",
+            "program": "npm",
+            "args": ["run", "dev"],
+            "working_directory": ".",
+            "event_hooks": [{
+                "name": "crash",
+                "event": { "DetectSubstring": { "stream": "Any", "contains": "crashed" } },
+                "action": "Restart",
+            }],
+        }))
+        .unwrap();
+
+        for format in [Format::Json, Format::Toml, Format::Yaml] {
+            let contents = format.to_string_pretty(&job).unwrap();
+            let (parsed, changes) = Job::parse_migrated(format, &contents).unwrap();
+            assert!(changes.is_empty(), "{format:?}: {changes:?}");
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::to_value(&job).unwrap(),
+                "{format:?}"
+            );
+        }
+    }
+}
@@ -1,40 +1,79 @@
+pub mod editor;
 pub mod event;
 pub mod filter;
+pub mod format;
 pub mod io;
+pub mod lines;
+pub mod migrate;
+pub mod name;
+pub mod outcome;
+pub mod output;
+pub mod project;
 pub mod run;
+pub mod schema;
+pub mod show;
 pub mod template;
 
-use crate::{colors::Tend, job::event::ControlFlow};
+use crate::{colors::JobColor, job::event::ControlFlow};
 use anyhow::Result;
-use folktime::Folktime;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 use tokio::{
-    io::{AsyncBufReadExt, BufReader, Lines},
     process::{ChildStderr, ChildStdout},
     sync::mpsc::Receiver,
 };
 
-use self::event::{Hook, RestartBehavior, RestartStrategy};
+use self::{
+    event::{Hook, RestartBehavior, RestartStrategy},
+    lines::OutputLines,
+};
 
 #[allow(clippy::unsafe_derive_deserialize)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Job {
+    /// Schema version of the job file; older files are upgraded when loaded.
+    #[serde(default)]
+    pub version: u32,
+    /// Unique name of the job, the same as its file name.
     pub name: String,
+    /// Whether the job runs with `tend run --all` and `--group`.
     #[serde(default = "return_true")]
     pub enabled: bool,
     pub group: String,
+    /// Executable to run.
     pub program: String,
+    /// Arguments passed to the program.
     pub args: Vec<String>,
     pub working_directory: PathBuf,
+    /// Environment variables set for the program.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// When to restart the job after it exits.
     #[serde(default)]
     pub restart: RestartBehavior,
+    /// How long to wait between automatic restarts.
     #[serde(default)]
     pub restart_strategy: RestartStrategy,
+    /// Actions to take when the output of the job matches.
     #[serde(default)]
     pub event_hooks: Vec<Hook>,
+    /// Name of the template whose hooks and environment variables the job uses.
     #[serde(default)]
-    pub template: Option<template::Template>,
+    pub template: Option<String>,
+    /// Values of the template's parameters.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub template_params: BTreeMap<String, String>,
+    /// Names of template hooks that do not apply to the job.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_template_hooks: Vec<String>,
+    /// Output lines longer than this many bytes are truncated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_line_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "output::OutputOptions::is_empty")]
+    pub output: output::OutputOptions,
+    /// Color of the job name in output; picked based on the name by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<JobColor>,
 }
 
 impl Job {
@@ -52,6 +91,33 @@ impl Job {
         }
     }
 
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+            .unwrap_or(lines::DEFAULT_MAX_LINE_LENGTH)
+    }
+
+    /// Checks that the job can be run before it is saved.
+    pub fn validate(&self) -> Result<()> {
+        name::validate(&self.name)?;
+        if self.program.trim().is_empty() {
+            anyhow::bail!("Program must not be empty.");
+        }
+        if self.group.trim().is_empty() {
+            anyhow::bail!("Group must not be empty.");
+        }
+        if !self.working_directory.is_dir() {
+            anyhow::bail!(
+                "Working directory {} does not exist or is not a directory.",
+                self.working_directory.display()
+            );
+        }
+        if self.max_line_length == Some(0) {
+            anyhow::bail!("Maximum line length must be greater than zero.");
+        }
+
+        Ok(())
+    }
+
     pub const fn restart_behaviour(&self) -> &'static str {
         match self.restart {
             RestartBehavior::Always => "always",
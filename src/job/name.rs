@@ -0,0 +1,101 @@
+use super::format::Format;
+use anyhow::Result;
+
+/// Longest job or template name allowed.
+pub const MAX_LENGTH: usize = 64;
+
+/// Names that Windows reserves for devices, in any case and with any extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Checks that a job name is safe to use as a file name in the jobs directory.
+///
+/// A job name is 1 to 64 ASCII letters, digits, `-`, `_` or `.`, starting with a letter or
+/// digit. This rules out path separators, `..`, hidden files and names Windows reserves.
+pub fn validate(name: &str) -> Result<()> {
+    check("Job", name)
+}
+
+/// Checks a template name, which follows the same rules as job names.
+pub fn validate_template(name: &str) -> Result<()> {
+    check("Template", name)
+}
+
+fn check(kind: &str, name: &str) -> Result<()> {
+    let help = format!(
+        "{kind} names may contain letters, digits, '-', '_' and '.', and must start with a letter or digit."
+    );
+    let lower = kind.to_lowercase();
+
+    let Some(first) = name.chars().next() else {
+        anyhow::bail!("{kind} name must not be empty.");
+    };
+    if name.len() > MAX_LENGTH {
+        anyhow::bail!("{kind} name '{name}' is longer than {MAX_LENGTH} characters.");
+    }
+    if !first.is_ascii_alphanumeric() {
+        anyhow::bail!("Invalid {lower} name '{name}'. {help}");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        anyhow::bail!("Invalid character {c:?} in {lower} name '{name}'. {help}");
+    }
+    if Format::has_extension(name) {
+        anyhow::bail!(
+            "{kind} name '{name}' must not end with a file extension such as .json, .toml or .yaml."
+        );
+    }
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED.contains(&stem.to_ascii_lowercase().as_str()) {
+        anyhow::bail!("{kind} name '{name}' is reserved on Windows.");
+    }
+
+    Ok(())
+}
+
+/// Parses a job name given on the command line.
+pub fn parse(name: &str) -> Result<String> {
+    validate(name)?;
+    Ok(name.to_string())
+}
+
+/// Parses a template name given on the command line.
+pub fn parse_template(name: &str) -> Result<String> {
+    validate_template(name)?;
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_names() {
+        for name in [
+            "web",
+            "api-1",
+            "db_backup",
+            "v1.2",
+            "9lives",
+            &"a".repeat(MAX_LENGTH),
+        ] {
+            assert!(validate(name).is_ok(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_paths_and_unsafe_names() {
+        let too_long = "a".repeat(MAX_LENGTH + 1);
+        for name in [
+            "", "../x", "..", "a/b", "a\\b", ".hidden", "-flag", "my job", "con", "NUL.txt",
+            "foo.json", "foo.yml", &too_long,
+        ] {
+            assert!(validate(name).is_err(), "{name:?}");
+            assert!(validate_template(name).is_err(), "{name:?}");
+        }
+    }
+}
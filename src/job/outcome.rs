@@ -0,0 +1,54 @@
+use std::{process::ExitStatus, time::Duration};
+
+/// Why a job stopped running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// The process exited successfully and the job does not restart after success.
+    Success,
+    /// The process failed and the job does not restart after failure.
+    Failure,
+    /// A hook stopped the job.
+    Hook(String),
+    /// The job was told to stop, e.g. by Ctrl+C.
+    Stopped,
+    /// The job could not be supervised, e.g. because the program could not be started.
+    Error(String),
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Success => write!(f, "exited successfully"),
+            Self::Failure => write!(f, "exited with failure"),
+            Self::Hook(hook) => write!(f, "stopped by hook {hook}"),
+            Self::Stopped => write!(f, "stopped"),
+            Self::Error(error) => write!(f, "error: {error}"),
+        }
+    }
+}
+
+/// How a job ended, summarized at the end of `tend run`.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub job: String,
+    pub runtime: Duration,
+    pub restarts: u64,
+    pub last_status: Option<ExitStatus>,
+    pub stop_reason: StopReason,
+}
+
+impl Outcome {
+    pub const fn new(job: String) -> Self {
+        Self {
+            job,
+            runtime: Duration::ZERO,
+            restarts: 0,
+            last_status: None,
+            stop_reason: StopReason::Stopped,
+        }
+    }
+
+    pub const fn failed(&self) -> bool {
+        matches!(self.stop_reason, StopReason::Failure | StopReason::Error(_))
+    }
+}
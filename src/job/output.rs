@@ -0,0 +1,454 @@
+use crate::colors::{JobColor, Tend};
+use chrono::SecondsFormat;
+use clap::ValueEnum;
+use colored::Colorize;
+use folktime::Folktime;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{fmt::Write, time::Duration};
+
+use super::event::Stream;
+
+#[derive(Default, Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Human readable output
+    #[default]
+    Text,
+    /// Newline-delimited JSON records of output lines and lifecycle events
+    Json,
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, ValueEnum, PartialEq, Eq,
+)]
+pub enum Timestamp {
+    None,
+    Local,
+    Utc,
+}
+
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    schemars::JsonSchema,
+    ValueEnum,
+    PartialEq,
+    Eq,
+)]
+pub enum TimestampPrecision {
+    #[default]
+    Seconds,
+    Millis,
+    Micros,
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, ValueEnum, PartialEq, Eq,
+)]
+pub enum StreamMarker {
+    /// Mark only lines printed to stderr
+    Stderr,
+    /// Mark every line with its stream
+    Always,
+    /// Never mark lines with their stream
+    Never,
+}
+
+/// How output lines of a job are prefixed.
+///
+/// Every field is optional so that options given to `tend run` can override the options saved
+/// with a job, which in turn override the defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema, clap::Args)]
+#[serde(default)]
+pub struct OutputOptions {
+    #[arg(long, help = "Prefix output with a timestamp in local time or UTC")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<Timestamp>,
+    #[arg(long, help = "Precision of timestamps")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_precision: Option<TimestampPrecision>,
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "true",
+        help = "Pad job names so that output of different jobs lines up"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align_names: Option<bool>,
+    #[arg(long, help = "Which output lines are marked with their stream")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_marker: Option<StreamMarker>,
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Custom prefix of output lines using {time}, {job} and {stream}, e.g. \"[{time}] {job}: \""
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+impl OutputOptions {
+    /// Whether no option is set.
+    pub const fn is_empty(&self) -> bool {
+        self.timestamps.is_none()
+            && self.timestamp_precision.is_none()
+            && self.align_names.is_none()
+            && self.stream_marker.is_none()
+            && self.prefix.is_none()
+    }
+
+    /// Fills the options that are not set from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: &Self) -> Self {
+        Self {
+            timestamps: self.timestamps.or(fallback.timestamps),
+            timestamp_precision: self.timestamp_precision.or(fallback.timestamp_precision),
+            align_names: self.align_names.or(fallback.align_names),
+            stream_marker: self.stream_marker.or(fallback.stream_marker),
+            prefix: self.prefix.or_else(|| fallback.prefix.clone()),
+        }
+    }
+}
+
+/// Prints output and status messages of a running job.
+#[derive(Debug, Clone)]
+pub struct Printer {
+    name: String,
+    name_width: usize,
+    color: JobColor,
+    stderr_color: JobColor,
+    timestamps: Timestamp,
+    precision: TimestampPrecision,
+    stream_marker: StreamMarker,
+    prefix: Option<String>,
+    mode: OutputMode,
+    verbose: bool,
+}
+
+impl Printer {
+    /// `name_width` is the length of the longest job name running, used when aligning names.
+    pub fn new(
+        name: &str,
+        options: &OutputOptions,
+        name_width: usize,
+        color: JobColor,
+        stderr_color: JobColor,
+        mode: OutputMode,
+        verbose: bool,
+    ) -> Self {
+        let align = options.align_names.unwrap_or(false);
+        Self {
+            name: name.to_string(),
+            name_width: if align { name_width } else { 0 },
+            color,
+            stderr_color,
+            timestamps: options.timestamps.unwrap_or(match mode {
+                OutputMode::Text => Timestamp::None,
+                OutputMode::Json => Timestamp::Utc,
+            }),
+            precision: options.timestamp_precision.unwrap_or(match mode {
+                OutputMode::Text => TimestampPrecision::Seconds,
+                OutputMode::Json => TimestampPrecision::Millis,
+            }),
+            stream_marker: options.stream_marker.unwrap_or(if verbose {
+                StreamMarker::Always
+            } else {
+                StreamMarker::Stderr
+            }),
+            prefix: options.prefix.clone(),
+            mode,
+            verbose,
+        }
+    }
+
+    fn timestamp(&self, timestamps: Timestamp) -> Option<String> {
+        let format = match self.precision {
+            TimestampPrecision::Seconds => SecondsFormat::Secs,
+            TimestampPrecision::Millis => SecondsFormat::Millis,
+            TimestampPrecision::Micros => SecondsFormat::Micros,
+        };
+        match timestamps {
+            Timestamp::None => None,
+            Timestamp::Local => Some(chrono::Local::now().to_rfc3339_opts(format, false)),
+            Timestamp::Utc => Some(chrono::Utc::now().to_rfc3339_opts(format, true)),
+        }
+    }
+
+    /// The job name to start status messages with, preceded by a timestamp if enabled.
+    fn job(&self) -> String {
+        self.timestamp(self.timestamps).map_or_else(
+            || self.name.job_with(self.color).to_string(),
+            |time| format!("{} {}", time.dimmed(), self.name.job_with(self.color)),
+        )
+    }
+
+    pub fn report(&self, report: &Report<'_>) {
+        match self.mode {
+            OutputMode::Text => self.print_text(report),
+            OutputMode::Json => {
+                let record = Record {
+                    timestamp: self.timestamp(self.timestamps).unwrap_or_default(),
+                    job: &self.name,
+                    report,
+                };
+                match serde_json::to_string(&record) {
+                    Ok(json) => println!("{json}"),
+                    Err(e) => eprintln!("{} could not serialize event: {e}", self.job()),
+                }
+            }
+        }
+    }
+
+    fn print_text(&self, report: &Report<'_>) {
+        let job = self.job();
+        match report {
+            Report::Starting { .. } => {
+                if self.verbose {
+                    println!("{job} starting");
+                }
+            }
+            Report::Output { stream, line } => println!("{}{}", self.prefix(*stream), line),
+            Report::ReadFailed { stream, error } => {
+                eprintln!("{job} could not read {}: {error}", stream_str(*stream));
+            }
+            Report::OutputStillOpen => {
+                if self.verbose {
+                    println!(
+                        "{job} output is still open after the process exited, not waiting for it"
+                    );
+                }
+            }
+            Report::HookTriggered { hook, action } => {
+                if self.verbose {
+                    println!("{job} triggered hook {hook} ({action})");
+                }
+            }
+            Report::Exited {
+                success: true,
+                runtime,
+                ..
+            } => println!(
+                "{job} process exited {} after {}",
+                "successfully".success(),
+                Folktime::duration(*runtime).to_string().time_value(),
+            ),
+            Report::Exited {
+                success: false,
+                status,
+                runtime,
+                ..
+            } => println!(
+                "{job} process exited with {} ({status}) after {}",
+                "failure".failure(),
+                Folktime::duration(*runtime).to_string().time_value(),
+            ),
+            Report::ExitStatusFailed { error, runtime, .. } => println!(
+                "{job} could not read process exit status after {}: {error}",
+                Folktime::duration(*runtime).to_string().time_value(),
+            ),
+            Report::Recovered {
+                runtime,
+                backoff_count_before,
+                backoff_count_after,
+            } => {
+                if self.verbose {
+                    println!(
+                        "{job} recovered after running for {} (backoff count: {backoff_count_before} -> {backoff_count_after})",
+                        Folktime::duration(*runtime).to_string().time_value(),
+                    );
+                }
+            }
+            Report::Restarting {
+                reason,
+                quick,
+                attempt,
+                delay_seconds,
+            } => {
+                let quickly = if *quick { " quickly" } else { "" };
+                if *delay_seconds != 0 {
+                    println!(
+                        "{job} restarting{quickly} in {} seconds ({reason}, attempt #{attempt})",
+                        delay_seconds.to_string().time_value(),
+                    );
+                } else {
+                    println!("{job} restarting{quickly} ({reason}, attempt #{attempt})");
+                }
+            }
+            Report::TerminationSignal => {
+                if self.verbose {
+                    println!("{job} received termination signal");
+                }
+            }
+            Report::Terminating => {
+                if self.verbose {
+                    println!("{job} terminating process");
+                }
+            }
+            Report::TerminateFailed { error } => {
+                eprintln!("{job} failed to stop process: {error}");
+            }
+            Report::Error { error } => eprintln!("{job} {}: {error}", "error".failure()),
+            Report::Stopping { reason } => {
+                if self.verbose {
+                    println!("{job} stopping ({reason})");
+                } else {
+                    println!();
+                }
+            }
+        }
+    }
+
+    // The placeholders of the prefix template look like format arguments.
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn prefix(&self, stream: Stream) -> String {
+        let marked = match self.stream_marker {
+            StreamMarker::Always => true,
+            StreamMarker::Never => false,
+            StreamMarker::Stderr => stream == Stream::Stderr,
+        };
+        let stream_name = match stream {
+            Stream::Stderr => stream_str(stream).job_with(self.stderr_color),
+            Stream::Stdout | Stream::Any => stream_str(stream).thick(),
+        };
+
+        if let Some(template) = &self.prefix {
+            // An explicit {time} asks for a timestamp even if timestamps are not enabled.
+            let time = self
+                .timestamp(match self.timestamps {
+                    Timestamp::None => Timestamp::Local,
+                    timestamps => timestamps,
+                })
+                .unwrap_or_default();
+            let name = format!("{:<width$}", self.name, width = self.name_width);
+            return template
+                .replace("{time}", &time.dimmed().to_string())
+                .replace("{job}", &name.job_with(self.color).to_string())
+                .replace("{stream}", &stream_name.to_string());
+        }
+
+        let mut prefix = String::new();
+        if let Some(time) = self.timestamp(self.timestamps) {
+            prefix.push_str(&time.dimmed().to_string());
+            prefix.push(' ');
+        }
+
+        let mut width = self.name.len();
+        if marked {
+            let _ = write!(
+                prefix,
+                "{}{}{}{}",
+                self.name.job_with(self.color),
+                " (".thick(),
+                stream_name,
+                ")".thick()
+            );
+            width += stream_name.len() + 3;
+        } else {
+            prefix.push_str(&self.name.job_with(self.color).to_string());
+        }
+        prefix.push_str(&":".job_with(self.color).to_string());
+
+        // Leave room for the longest name and a stream marker so that output lines up.
+        if self.name_width != 0 {
+            let marker_width = if self.stream_marker == StreamMarker::Never {
+                0
+            } else {
+                9
+            };
+            let padding = (self.name_width + marker_width).saturating_sub(width);
+            prefix.push_str(&" ".repeat(padding));
+        }
+        prefix.push(' ');
+
+        prefix
+    }
+}
+
+/// Something that happened to a running job.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Report<'a> {
+    Starting {
+        attempt: u64,
+    },
+    Output {
+        #[serde(serialize_with = "serialize_stream")]
+        stream: Stream,
+        line: &'a str,
+    },
+    ReadFailed {
+        #[serde(serialize_with = "serialize_stream")]
+        stream: Stream,
+        error: String,
+    },
+    OutputStillOpen,
+    HookTriggered {
+        hook: &'a str,
+        action: &'static str,
+    },
+    Exited {
+        attempt: u64,
+        success: bool,
+        exit_code: Option<i32>,
+        status: String,
+        #[serde(rename = "runtime_ms", serialize_with = "serialize_millis")]
+        runtime: Duration,
+    },
+    ExitStatusFailed {
+        attempt: u64,
+        error: String,
+        #[serde(rename = "runtime_ms", serialize_with = "serialize_millis")]
+        runtime: Duration,
+    },
+    Recovered {
+        #[serde(rename = "runtime_ms", serialize_with = "serialize_millis")]
+        runtime: Duration,
+        backoff_count_before: u64,
+        backoff_count_after: u64,
+    },
+    Restarting {
+        reason: &'a str,
+        quick: bool,
+        attempt: u64,
+        delay_seconds: u64,
+    },
+    TerminationSignal,
+    Terminating,
+    TerminateFailed {
+        error: String,
+    },
+    Stopping {
+        reason: &'a str,
+    },
+    Error {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    job: &'a str,
+    #[serde(flatten)]
+    report: &'a Report<'a>,
+}
+
+const fn stream_str(stream: Stream) -> &'static str {
+    match stream {
+        Stream::Stdout => "stdout",
+        Stream::Stderr => "stderr",
+        Stream::Any => "output",
+    }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // Signature required by serde.
+fn serialize_stream<S: Serializer>(stream: &Stream, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(stream_str(*stream))
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
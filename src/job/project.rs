@@ -0,0 +1,187 @@
+use super::{Job, filter::Filter, migrate, name};
+use crate::colors::Tend;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Name of the project file `tend` looks for in the current directory and its parents.
+pub const FILE_NAME: &str = "tend.toml";
+
+/// Where to take jobs from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sources {
+    /// Saved jobs and the jobs of the project file, if there is one.
+    All,
+    /// Only the jobs of the project file.
+    Project,
+    /// Only saved jobs.
+    Global,
+}
+
+/// Jobs defined in a project file, usually committed to a repository.
+///
+/// ```toml
+/// [jobs.api]
+/// program = "cargo"
+/// args = ["run"]
+/// working_directory = "api"
+/// ```
+///
+/// Working directories are relative to the project file and default to its directory.
+#[derive(Debug)]
+pub struct Project {
+    pub path: PathBuf,
+    pub jobs: Vec<Job>,
+}
+
+impl Project {
+    /// Finds the project file in the current directory or the closest parent directory.
+    pub fn find() -> Result<Option<PathBuf>> {
+        let current_dir = std::env::current_dir()?;
+        Ok(current_dir
+            .ancestors()
+            .map(|dir| dir.join(FILE_NAME))
+            .find(|path| path.is_file()))
+    }
+
+    /// Loads the project file closest to the current directory, if there is one.
+    pub fn discover() -> Result<Option<Self>> {
+        Self::find()?.map(|path| Self::load(&path)).transpose()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read project file {}", path.display()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut file: toml::Table = toml::from_str(&contents)
+            .with_context(|| format!("Invalid project file {}", path.display()))?;
+        let tables = match file.remove("jobs") {
+            None => toml::Table::new(),
+            Some(toml::Value::Table(tables)) => tables,
+            Some(_) => anyhow::bail!(
+                "Invalid project file {}: `jobs` must be a table of jobs",
+                path.display()
+            ),
+        };
+
+        let mut jobs = vec![];
+        for (job_name, table) in tables {
+            let job = Self::job(&job_name, table, dir).with_context(|| {
+                format!(
+                    "Invalid job '{job_name}' in project file {}",
+                    path.display()
+                )
+            })?;
+            jobs.push(job);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            jobs,
+        })
+    }
+
+    fn job(job_name: &str, table: toml::Value, dir: &Path) -> Result<Job> {
+        name::validate(job_name)?;
+        let toml::Value::Table(mut table) = table else {
+            anyhow::bail!("a job must be a table");
+        };
+
+        // The name comes from the table key; the rest defaults like `tend create` does.
+        let own_program = table.contains_key("program");
+        table.insert("name".to_string(), job_name.into());
+        table.entry("group").or_insert_with(|| "default".into());
+        table.entry("program").or_insert_with(|| "".into());
+        table
+            .entry("args")
+            .or_insert_with(|| toml::Value::Array(vec![]));
+        table
+            .entry("working_directory")
+            .or_insert_with(|| ".".into());
+
+        let mut value = serde_json::to_value(table)?;
+        migrate::migrate(&mut value)?;
+        let mut job: Job = serde_json::from_value(value.clone())?;
+        job.working_directory = if job.working_directory == Path::new(".") {
+            dir.to_path_buf()
+        } else {
+            dir.join(&job.working_directory)
+        };
+
+        // Settings left out of the table come from the template, as with `tend create`.
+        if let Some(template) = job.load_template()? {
+            if value.get("restart").is_none() {
+                job.restart = template.restart.unwrap_or(job.restart);
+            }
+            if value.get("restart_strategy").is_none() {
+                job.restart_strategy = template.restart_strategy.unwrap_or(job.restart_strategy);
+            }
+            if value.get("max_line_length").is_none() {
+                job.max_line_length = template.max_line_length;
+            }
+            if !own_program && let Some(program) = template.program {
+                job.program = program;
+                job.args = template.args;
+            }
+        }
+        if job.program.is_empty() {
+            anyhow::bail!("missing field `program`; it is only optional when the template sets it");
+        }
+
+        Ok(job)
+    }
+
+    /// Jobs of the project matching the filter.
+    pub fn filtered(self, filter: &Filter, include_disabled: bool) -> Vec<Job> {
+        self.jobs
+            .into_iter()
+            .filter(|job| include_disabled || job.enabled)
+            .filter(|job| filter.matches(job))
+            .collect()
+    }
+}
+
+impl Job {
+    /// Collects the jobs matching the filter from the given sources.
+    ///
+    /// Project jobs take the place of saved jobs with the same name.
+    pub fn collect(
+        filter: &Filter,
+        include_disabled: bool,
+        sources: Sources,
+        verbose: bool,
+    ) -> Result<Vec<Self>> {
+        let mut jobs = vec![];
+        if sources != Sources::Project {
+            Self::iterate_jobs_filtered(|job| jobs.push(job), filter, include_disabled, verbose)?;
+        }
+
+        if sources != Sources::Global {
+            let project = match Project::discover() {
+                Ok(project) => project,
+                Err(e) if sources == Sources::Project => return Err(e),
+                Err(e) => {
+                    // A broken project file must not keep saved jobs from being used.
+                    eprintln!("{} {e:#}", "Ignoring project file:".failure());
+                    None
+                }
+            };
+            match project {
+                Some(project) => {
+                    if verbose {
+                        eprintln!("Using project file {}", project.path.display());
+                    }
+                    let project_jobs = project.filtered(filter, include_disabled);
+                    jobs.retain(|job| !project_jobs.iter().any(|p| p.name == job.name));
+                    jobs.extend(project_jobs);
+                }
+                None if sources == Sources::Project => {
+                    anyhow::bail!("No {FILE_NAME} found in the current directory or its parents.")
+                }
+                None => (),
+            }
+        }
+
+        Ok(jobs)
+    }
+}
@@ -1,99 +1,201 @@
 use super::{
-    AsyncBufReadExt, BufReader, ChildStderr, ChildStdout, ControlFlow, Folktime, Job, Lines,
-    Receiver, Result, Tend,
+    ChildStderr, ChildStdout, ControlFlow, Job, OutputLines, Receiver, Result,
+    event::Stream,
+    outcome::{Outcome, StopReason},
+    output::{Printer, Report},
 };
 
 use process_wrap::tokio::{ChildWrapper, CommandWrap};
 
+/// How long to keep reading output after the process exited.
+const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
 impl Job {
     fn duration(start_time: std::time::Instant) -> std::time::Duration {
         let end_time = std::time::Instant::now();
         end_time.duration_since(start_time)
     }
 
+    fn read_result(
+        printer: &Printer,
+        result: std::io::Result<Option<String>>,
+        stream: Stream,
+    ) -> Option<String> {
+        match result {
+            Ok(line) => line,
+            Err(error) => {
+                printer.report(&Report::ReadFailed {
+                    stream,
+                    error: error.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    fn output_line<'a>(&'a self, stream: Stream, line: &str, printer: &Printer) -> ControlFlow<'a> {
+        printer.report(&Report::Output { stream, line });
+
+        let control = match stream {
+            Stream::Stderr => self.stderr_line_callback(line),
+            Stream::Stdout | Stream::Any => self.stdout_line_callback(line),
+        };
+
+        let (hook, action) = match control {
+            ControlFlow::Nothing | ControlFlow::Finished(_) => return control,
+            ControlFlow::RestartCommand(hook) => (hook, "restart"),
+            ControlFlow::FastRestartCommand(hook) => (hook, "fast restart"),
+            ControlFlow::StopJob(hook) => (hook, "stop"),
+        };
+        printer.report(&Report::HookTriggered { hook, action });
+
+        control
+    }
+
+    /// Prints output still buffered in the pipes after the process exited.
+    ///
+    /// Grandchildren may inherit the pipes and keep them open, so this gives up after
+    /// [`DRAIN_TIMEOUT`] and stops reading the streams. Returns the first hook triggered by the
+    /// remaining output.
+    async fn drain_output<'a>(
+        &'a self,
+        stdout: &mut OutputLines<ChildStdout>,
+        stderr: &mut OutputLines<ChildStderr>,
+        printer: &Printer,
+    ) -> ControlFlow<'a> {
+        let mut hook = ControlFlow::Nothing;
+
+        let drain = async {
+            loop {
+                let control = tokio::select! {
+                    stdout_line = stdout.next_line(), if !stdout.is_done() => {
+                        Self::read_result(printer, stdout_line, Stream::Stdout)
+                            .map_or(ControlFlow::Nothing, |line| self.output_line(Stream::Stdout, &line, printer))
+                    }
+                    stderr_line = stderr.next_line(), if !stderr.is_done() => {
+                        Self::read_result(printer, stderr_line, Stream::Stderr)
+                            .map_or(ControlFlow::Nothing, |line| self.output_line(Stream::Stderr, &line, printer))
+                    }
+                    else => break,
+                };
+
+                if hook == ControlFlow::Nothing {
+                    hook = control;
+                }
+            }
+        };
+
+        if tokio::time::timeout(DRAIN_TIMEOUT, drain).await.is_err() {
+            printer.report(&Report::OutputStillOpen);
+            stdout.abandon();
+            stderr.abandon();
+        }
+
+        hook
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn wait_for_something<'a>(
         &'a self,
         process: &mut Box<dyn ChildWrapper>,
         rx: &mut Receiver<()>,
-        verbose: bool,
-        stdout: &mut Lines<BufReader<ChildStdout>>,
-        stderr: &mut Lines<BufReader<ChildStderr>>,
+        printer: &Printer,
+        outcome: &mut Outcome,
+        stdout: &mut OutputLines<ChildStdout>,
+        stderr: &mut OutputLines<ChildStderr>,
         start_time: std::time::Instant,
     ) -> Result<ControlFlow<'a>> {
         // let process = process.inner_child();
 
         tokio::select! {
-            stdout_line = stdout.next_line() => {
-                if let Some(line) = stdout_line? {
-                    if verbose {
-                        println!("{}{}{}{}", self.name.job(), " (stdout)".thick(), ": ".job(), line);
-                    }else {
-                        println!("{}{}", format!("{}: ", self.name).job(), line);
-                    }
-                    return Ok(self.stdout_line_callback(&line, verbose));
-                }
-                Ok(ControlFlow::Nothing)
+            stdout_line = stdout.next_line(), if !stdout.is_done() => {
+                Ok(Self::read_result(printer, stdout_line, Stream::Stdout)
+                    .map_or(ControlFlow::Nothing, |line| self.output_line(Stream::Stdout, &line, printer)))
             }
-            stderr_line = stderr.next_line() => {
-                if let Some(line) = stderr_line? {
-                    println!("{}{}{}{}", self.name.job(), " (stderr)".failure(), ": ".job(), line);
-                    return Ok(self.stderr_line_callback(&line, verbose));
-                }
-                Ok(ControlFlow::Nothing)
+            stderr_line = stderr.next_line(), if !stderr.is_done() => {
+                Ok(Self::read_result(printer, stderr_line, Stream::Stderr)
+                    .map_or(ControlFlow::Nothing, |line| self.output_line(Stream::Stderr, &line, printer)))
             }
             a = process.wait() => {
-                let runtime = Folktime::duration(Self::duration(start_time)).to_string();
+                let runtime = Self::duration(start_time);
+                let hook = self.drain_output(stdout, stderr, printer).await;
+                if let Ok(status) = a {
+                    outcome.last_status = Some(status);
+                }
                 match a {
                     Ok(status) if status.success() => {
-                        println!(
-                            "{} process exited {} after {}",
-                            self.name.job(),
-                            "successfully".success(),
-                            runtime.time_value(),
-                        );
+                        printer.report(&Report::Exited {
+                            attempt: outcome.restarts + 1,
+                            success: true,
+                            exit_code: status.code(),
+                            status: status.to_string(),
+                            runtime,
+                        });
+                        if hook != ControlFlow::Nothing {
+                            return Ok(hook);
+                        }
                         return if self.restart_on_success() {
                             Ok(ControlFlow::RestartCommand("success"))
                         } else {
-                            Ok(ControlFlow::StopJob("success"))
+                            Ok(ControlFlow::Finished(StopReason::Success))
                         };
                     }
                     Ok(status) => {
-                        println!(
-                            "{} process exited with {} ({status}) after {}",
-                            self.name.job(),
-                            "failure".failure(),
-                            runtime.time_value(),
-                        );
+                        printer.report(&Report::Exited {
+                            attempt: outcome.restarts + 1,
+                            success: false,
+                            exit_code: status.code(),
+                            status: status.to_string(),
+                            runtime,
+                        });
                     }
                     Err(error) => {
-                        println!(
-                            "{} could not read process exit status after {}: {error}",
-                            self.name.job(),
-                            runtime.time_value(),
-                        );
+                        printer.report(&Report::ExitStatusFailed {
+                            attempt: outcome.restarts + 1,
+                            error: error.to_string(),
+                            runtime,
+                        });
                     }
                 }
 
-                if self.restart_on_failure() {
+                if hook != ControlFlow::Nothing {
+                    Ok(hook)
+                } else if self.restart_on_failure() {
                     Ok(ControlFlow::RestartCommand("failure"))
                 } else {
-                    Ok(ControlFlow::StopJob("failure"))
+                    Ok(ControlFlow::Finished(StopReason::Failure))
                 }
             }
             _ = rx.recv() => {
-                if verbose {
-                    println!("{} received termination signal", self.name.job());
-                }
-                let _ = process.start_kill();
-                let _ = process.wait().await;
-                Ok(ControlFlow::StopJob("termination signal"))
+                printer.report(&Report::TerminationSignal);
+                Ok(ControlFlow::Finished(StopReason::Stopped))
             }
         }
     }
 
+    /// Runs the job until it stops, restarting it as configured.
+    pub async fn create_repeated_process(self, mut rx: Receiver<()>, printer: Printer) -> Outcome {
+        let mut outcome = Outcome::new(self.name.clone());
+        let start_time = std::time::Instant::now();
+
+        if let Err(e) = self.supervise(&mut rx, &printer, &mut outcome).await {
+            printer.report(&Report::Error {
+                error: e.to_string(),
+            });
+            outcome.stop_reason = StopReason::Error(e.to_string());
+        }
+
+        outcome.runtime = Self::duration(start_time);
+        outcome
+    }
+
     #[allow(clippy::too_many_lines)]
-    pub async fn create_repeated_process(self, mut rx: Receiver<()>, verbose: bool) -> Result<()> {
+    async fn supervise(
+        &self,
+        rx: &mut Receiver<()>,
+        printer: &Printer,
+        outcome: &mut Outcome,
+    ) -> Result<()> {
         let mut backoff_restart_count = 0;
         let mut fast_backoff_restart_count = 0;
 
@@ -102,6 +204,7 @@ impl Job {
                 command
                     .current_dir(&self.working_directory)
                     .args(&self.args)
+                    .envs(&self.env)
                     .stdout(std::process::Stdio::piped())
                     .stderr(std::process::Stdio::piped());
             });
@@ -115,33 +218,34 @@ impl Job {
 
             let mut process = command.spawn()?;
 
-            if verbose {
-                println!("{} starting", self.name.job());
-            }
+            printer.report(&Report::Starting {
+                attempt: outcome.restarts + 1,
+            });
             let start_time = std::time::Instant::now();
 
-            let mut stdout = BufReader::new(
+            let mut stdout = OutputLines::new(
                 process
                     .stdout()
                     .take()
                     .ok_or_else(|| anyhow::anyhow!("Could not get stdout"))?,
-            )
-            .lines();
+                self.max_line_length(),
+            );
 
-            let mut stderr = BufReader::new(
+            let mut stderr = OutputLines::new(
                 process
                     .stderr()
                     .take()
                     .ok_or_else(|| anyhow::anyhow!("Could not get stderr"))?,
-            )
-            .lines();
+                self.max_line_length(),
+            );
 
             loop {
                 let control = self
                     .wait_for_something(
                         &mut process,
-                        &mut rx,
-                        verbose,
+                        rx,
+                        printer,
+                        outcome,
                         &mut stdout,
                         &mut stderr,
                         start_time,
@@ -165,81 +269,79 @@ impl Job {
                     let recovery_amount = (run_duration.as_secs() / 60).min(backoff_restart_count);
                     backoff_restart_count = backoff_restart_count.saturating_sub(recovery_amount);
 
-                    if verbose && old_count != backoff_restart_count {
-                        println!(
-                            "{} recovered after running for {} (backoff count: {} -> {})",
-                            self.name.job(),
-                            Folktime::duration(run_duration).to_string().time_value(),
-                            old_count,
-                            backoff_restart_count
-                        );
+                    if old_count != backoff_restart_count {
+                        printer.report(&Report::Recovered {
+                            runtime: run_duration,
+                            backoff_count_before: old_count,
+                            backoff_count_after: backoff_restart_count,
+                        });
                     }
                 }
 
+                // Stop the old process before waiting for the restart delay, reading whatever
+                // output it printed so that it does not block on a full pipe in the meantime.
+                Self::terminate_process(&mut process, printer).await?;
+                // Output is already drained when the process exited by itself.
+                if !(stdout.is_done() && stderr.is_done()) {
+                    self.drain_output(&mut stdout, &mut stderr, printer).await;
+                }
+
                 match control {
                     ControlFlow::Nothing => (),
                     ControlFlow::RestartCommand(reason) => {
                         let delay_seconds =
                             self.restart_strategy.delay_seconds(backoff_restart_count);
-                        if delay_seconds != 0 {
-                            println!(
-                                "{} restarting in {} seconds ({}, attempt #{})",
-                                self.name.job(),
-                                delay_seconds.to_string().time_value(),
-                                reason,
-                                backoff_restart_count + 1,
-                            );
-                            tokio::time::sleep(tokio::time::Duration::from_secs(delay_seconds))
-                                .await;
-                        } else {
-                            println!(
-                                "{} restarting ({}, attempt #{})",
-                                self.name.job(),
-                                reason,
-                                backoff_restart_count + 1
-                            );
-                        }
-                        self.terminate_process(&mut process, verbose).await?;
+                        printer.report(&Report::Restarting {
+                            reason,
+                            quick: false,
+                            attempt: backoff_restart_count + 1,
+                            delay_seconds,
+                        });
 
                         backoff_restart_count += 1;
 
-                        continue 'job;
+                        if Self::restart_delay(delay_seconds, rx, printer).await {
+                            outcome.restarts += 1;
+                            continue 'job;
+                        }
+                        outcome.stop_reason = StopReason::Stopped;
+                        break 'job;
                     }
                     ControlFlow::FastRestartCommand(reason) => {
                         let delay_seconds = self
                             .restart_strategy
                             .delay_seconds_fast(fast_backoff_restart_count);
-                        if delay_seconds != 0 {
-                            println!(
-                                "{} restarting quickly in {} seconds ({}, attempt #{})",
-                                self.name.job(),
-                                delay_seconds.to_string().time_value(),
-                                reason,
-                                fast_backoff_restart_count + 1,
-                            );
-                            tokio::time::sleep(tokio::time::Duration::from_secs(delay_seconds))
-                                .await;
-                        } else {
-                            println!(
-                                "{} restarting quickly ({}, attempt #{})",
-                                self.name.job(),
-                                reason,
-                                fast_backoff_restart_count + 1
-                            );
-                        }
-                        self.terminate_process(&mut process, verbose).await?;
+                        printer.report(&Report::Restarting {
+                            reason,
+                            quick: true,
+                            attempt: fast_backoff_restart_count + 1,
+                            delay_seconds,
+                        });
 
                         fast_backoff_restart_count += 1;
 
-                        continue 'job;
-                    }
-                    ControlFlow::StopJob(reason) => {
-                        if verbose {
-                            println!("{} stopping ({})", self.name.job(), reason);
-                        } else {
-                            println!();
+                        if Self::restart_delay(delay_seconds, rx, printer).await {
+                            outcome.restarts += 1;
+                            continue 'job;
                         }
-                        self.terminate_process(&mut process, verbose).await?;
+                        outcome.stop_reason = StopReason::Stopped;
+                        break 'job;
+                    }
+                    ControlFlow::StopJob(hook) => {
+                        outcome.stop_reason = StopReason::Hook(hook.to_string());
+                        printer.report(&Report::Stopping { reason: hook });
+                        break 'job;
+                    }
+                    ControlFlow::Finished(reason) => {
+                        printer.report(&Report::Stopping {
+                            reason: match reason {
+                                StopReason::Success => "success",
+                                StopReason::Failure => "failure",
+                                StopReason::Stopped => "termination signal",
+                                StopReason::Hook(_) | StopReason::Error(_) => "error",
+                            },
+                        });
+                        outcome.stop_reason = reason;
                         break 'job;
                     }
                 }
@@ -249,21 +351,29 @@ impl Job {
         Ok(())
     }
 
+    /// Waits before restarting the job. Returns `false` if the job was told to stop meanwhile.
+    async fn restart_delay(delay_seconds: u64, rx: &mut Receiver<()>, printer: &Printer) -> bool {
+        tokio::select! {
+            biased;
+
+            _ = rx.recv() => {
+                printer.report(&Report::TerminationSignal);
+                false
+            }
+            () = tokio::time::sleep(tokio::time::Duration::from_secs(delay_seconds)) => true,
+        }
+    }
+
     async fn terminate_process(
-        &self,
         process: &mut Box<dyn ChildWrapper>,
-        verbose: bool,
+        printer: &Printer,
     ) -> Result<()> {
-        if verbose {
-            println!("{} terminating process", self.name.job());
-        }
+        printer.report(&Report::Terminating);
 
         if let Err(e) = process.start_kill() {
-            eprintln!("{} failed to stop process: {}", self.name.job(), e);
-        }
-
-        if verbose {
-            println!("{} waiting for process to terminate", self.name.job());
+            printer.report(&Report::TerminateFailed {
+                error: e.to_string(),
+            });
         }
 
         process.wait().await?;
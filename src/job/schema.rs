@@ -0,0 +1,50 @@
+use super::{Job, template::Template};
+use serde_json::{Value, json};
+
+/// Fields of a job that a project file fills in, or takes from the template, when they are missing.
+const PROJECT_DEFAULTS: &[&str] = &["name", "group", "program", "args", "working_directory"];
+
+/// JSON Schema of a saved job file, generated from [`Job`].
+pub fn job() -> Value {
+    schemars::schema_for!(Job).to_value()
+}
+
+/// JSON Schema of a template file, generated from [`Template`].
+pub fn template() -> Value {
+    schemars::schema_for!(Template).to_value()
+}
+
+/// JSON Schema of a `tend.toml` project file.
+pub fn project() -> Value {
+    let mut job = job();
+    let defs = job
+        .as_object_mut()
+        .and_then(|job| job.remove("$defs"))
+        .unwrap_or_else(|| json!({}));
+
+    if let Some(job) = job.as_object_mut() {
+        job.remove("$schema");
+        job.remove("title");
+        if let Some(Value::Array(required)) = job.get_mut("required") {
+            required.retain(|field| {
+                !field
+                    .as_str()
+                    .is_some_and(|field| PROJECT_DEFAULTS.contains(&field))
+            });
+        }
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "tend project file",
+        "type": "object",
+        "properties": {
+            "jobs": {
+                "description": "Jobs of the project, by name.",
+                "type": "object",
+                "additionalProperties": job,
+            },
+        },
+        "$defs": defs,
+    })
+}
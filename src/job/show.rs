@@ -0,0 +1,198 @@
+use super::{
+    Job,
+    event::{Hook, RestartStrategy},
+};
+use crate::colors::Tend;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// How many restarts of the backoff schedule to show before it levels off.
+const SCHEDULE_LENGTH: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum, PartialEq, Eq)]
+pub enum ShowFormat {
+    #[default]
+    Human,
+    Json,
+    Toml,
+}
+
+/// Complete definition of a job together with the details derived from it.
+#[derive(Debug, Serialize)]
+struct Details<'a> {
+    #[serde(flatten)]
+    job: &'a Job,
+    path: PathBuf,
+    /// Hooks and environment variables the template adds when the job runs.
+    template_hooks: Vec<&'a Hook>,
+    template_env: BTreeMap<&'a str, &'a str>,
+    backoff: Backoff,
+}
+
+/// Delays in seconds before each of the first restarts.
+#[derive(Debug, Serialize)]
+struct Backoff {
+    restart: Vec<u64>,
+    fast_restart: Vec<u64>,
+}
+
+impl Backoff {
+    fn new(strategy: RestartStrategy) -> Self {
+        Self {
+            restart: (0..SCHEDULE_LENGTH)
+                .map(|restarts| strategy.delay_seconds(restarts))
+                .collect(),
+            fast_restart: (0..SCHEDULE_LENGTH)
+                .map(|restarts| strategy.delay_seconds_fast(restarts))
+                .collect(),
+        }
+    }
+}
+
+impl Job {
+    pub fn show(name: &str, format: ShowFormat, verbose: bool) -> Result<()> {
+        let job = Self::load(name, verbose)
+            .ok_or_else(|| anyhow::anyhow!("Job '{name}' was not found or could not be loaded."))?;
+
+        let template = job.load_template()?.unwrap_or_default();
+        let details = Details {
+            job: &job,
+            path: Self::path(name)?,
+            template_hooks: job.active_template_hooks(&template),
+            template_env: template
+                .env
+                .iter()
+                .filter(|(key, _)| !job.env.contains_key(*key))
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect(),
+            backoff: Backoff::new(job.restart_strategy),
+        };
+
+        match format {
+            ShowFormat::Human => details.print(),
+            ShowFormat::Json => println!("{}", serde_json::to_string_pretty(&details)?),
+            ShowFormat::Toml => print!("{}", toml::to_string_pretty(&details)?),
+        }
+
+        Ok(())
+    }
+}
+
+impl Details<'_> {
+    fn print(&self) {
+        let job = self.job;
+        field("Job", &job.name.job());
+        field("Enabled", if job.enabled { "yes" } else { "no" });
+        field("Group", &job.group);
+        field("Program", &job.program);
+        field("Args", &format!("{:?}", job.args));
+        field(
+            "Working directory",
+            &job.working_directory.display().to_string(),
+        );
+        if job.env.is_empty() && self.template_env.is_empty() {
+            field("Environment", "none");
+        } else {
+            println!("{}", "Environment:".thick());
+            for (key, value) in &job.env {
+                println!("  {key}={value}");
+            }
+            for (key, value) in &self.template_env {
+                println!("  {key}={value} (from template)");
+            }
+        }
+        field("Restart", job.restart_behaviour());
+        field(
+            "Restart strategy",
+            match job.restart_strategy {
+                RestartStrategy::Immediate => "immediate",
+                RestartStrategy::ExponentialBackoff => "exponential backoff",
+            },
+        );
+        field("Backoff", &schedule(&self.backoff.restart));
+        field("Fast backoff", &schedule(&self.backoff.fast_restart));
+        field(
+            "Template",
+            &match &job.template {
+                None => "none".to_string(),
+                Some(template) if job.template_params.is_empty() => template.clone(),
+                Some(template) => format!(
+                    "{template} ({})",
+                    job.template_params
+                        .iter()
+                        .map(|(name, value)| format!("{name}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            },
+        );
+        field(
+            "Max line length",
+            &job.max_line_length.map_or_else(
+                || format!("{} (default)", job.max_line_length()),
+                |length| length.to_string(),
+            ),
+        );
+        field(
+            "Color",
+            &job.color.map_or_else(
+                || "based on the name".to_string(),
+                |color| value_name(&color),
+            ),
+        );
+        field("File", &self.path.display().to_string());
+
+        if job.event_hooks.is_empty() && self.template_hooks.is_empty() {
+            field("Hooks", "none");
+        } else {
+            println!("{}", "Hooks:".thick());
+            for hook in &job.event_hooks {
+                println!("  {hook}");
+            }
+            for hook in &self.template_hooks {
+                println!("  {hook} (from template)");
+            }
+        }
+        if !job.disabled_template_hooks.is_empty() {
+            field("Disabled hooks", &job.disabled_template_hooks.join(", "));
+        }
+    }
+}
+
+/// Prints one labelled line of a definition.
+pub fn field(name: &str, value: &str) {
+    println!("{:<18} {value}", format!("{name}:").thick());
+}
+
+/// Formats a backoff schedule, e.g. `0s, 0s, 1s, 2s, then 4s`.
+fn schedule(delays: &[u64]) -> String {
+    if delays.iter().all(|&delay| delay == 0) {
+        return "no delay".to_string();
+    }
+
+    // Once the delay levels off, show it only once.
+    let mut delays = delays;
+    while let Some((last, rest)) = delays.split_last()
+        && rest.last() == Some(last)
+    {
+        delays = rest;
+    }
+
+    let delays = delays
+        .iter()
+        .map(|delay| format!("{delay}s"))
+        .collect::<Vec<_>>();
+    match delays.split_last() {
+        Some((last, rest)) => format!("{}, then {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+/// Name of a value as it is written on the command line.
+fn value_name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string())
+}
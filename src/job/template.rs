@@ -1,76 +1,780 @@
-use clap::ValueEnum;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
 
+use super::show::{ShowFormat, field};
 use super::{
     Hook, Job,
-    event::{Action, Event, RestartStrategy, Stream},
+    event::{Action, Event, RestartBehavior, RestartStrategy, Stream},
+    format::Format,
+    name,
+};
+use crate::{colors::Tend, config};
+use tabled::{
+    builder::Builder,
+    settings::{Color, Modify, Style, object::Rows},
 };
 
-#[derive(Copy, Clone, Debug, ValueEnum, Serialize, Deserialize)]
-pub enum Template {
-    PortForward,
+/// Settings and hooks shared by jobs, applied with `tend create --template <NAME>`.
+///
+/// The restart settings and maximum line length are copied into a job when the template is
+/// applied to it. The environment variables and hooks are not saved with the job; they are
+/// layered under the job's own whenever it runs.
+///
+/// Besides the built-in templates, users can define templates as files in the `templates`
+/// directory of the tend home directory, in any of the job file formats. A user template
+/// with the name of a built-in template replaces it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Template {
+    /// What the template is for.
+    pub description: Option<String>,
+    /// Parameters given with `--param NAME=VALUE`, used as `{NAME}` in the program, arguments,
+    /// environment variables and hook texts. Write `{{` and `}}` for literal braces.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Param>,
+    /// Program run by jobs created without one.
+    pub program: Option<String>,
+    /// Arguments passed to the template's program.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// When to restart jobs after they exit.
+    pub restart: Option<RestartBehavior>,
+    /// How long to wait between automatic restarts.
+    pub restart_strategy: Option<RestartStrategy>,
+    /// Output lines longer than this many bytes are truncated.
+    pub max_line_length: Option<usize>,
+    /// Environment variables set for the program; the job's own variables take precedence.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Hooks added to jobs; a job's own hook with the same name replaces the template's.
+    pub event_hooks: Vec<Hook>,
 }
 
-impl Job {
-    pub fn apply_template(&mut self, template: Template) {
+/// Parameter of a template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Param {
+    /// What the value is used for.
+    pub description: Option<String>,
+    /// Value used when none is given; the parameter is required without one.
+    pub default: Option<String>,
+}
+
+/// Where a template is defined.
+#[derive(Debug, Clone)]
+pub enum Source {
+    BuiltIn,
+    File(PathBuf),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BuiltIn => write!(f, "built-in"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Names of the built-in templates.
+const BUILT_IN: &[&str] = &[
+    "dev-server",
+    "docker-compose",
+    "kubectl-logs",
+    "port-forward",
+    "ssh-tunnel",
+];
+
+impl Template {
+    /// Directory user templates are loaded from.
+    pub fn dir() -> Result<PathBuf> {
+        Ok(config::home()?.join("templates"))
+    }
+
+    /// Loads a template by name, preferring user templates over built-in ones.
+    pub fn load(template: &str) -> Result<(Self, Source)> {
+        name::validate_template(template)?;
+
+        let dir = Self::dir()?;
+        for (file_name, format) in Format::candidates(template) {
+            let path = dir.join(file_name);
+            if path.is_file() {
+                let contents = std::fs::read_to_string(&path)?;
+                let loaded = format
+                    .parse_document(&contents)
+                    .with_context(|| format!("Invalid template file {}", path.display()))?;
+                return Ok((loaded, Source::File(path)));
+            }
+        }
+
+        Self::built_in(template)
+            .map(|built_in| (built_in, Source::BuiltIn))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Template '{template}' was not found. Use tend template list to see the available templates."
+                )
+            })
+    }
+
+    /// Names of all available templates and where each is defined, sorted by name.
+    pub fn list() -> Result<Vec<(String, Source)>> {
+        let mut templates: BTreeMap<String, Source> = BUILT_IN
+            .iter()
+            .map(|name| ((*name).to_string(), Source::BuiltIn))
+            .collect();
+
+        match std::fs::read_dir(Self::dir()?) {
+            Ok(entries) => {
+                for entry in entries {
+                    let entry = entry?;
+                    if !entry.path().is_file() {
+                        continue;
+                    }
+                    if let Ok(file_name) = entry.file_name().into_string() {
+                        let (template, _) = Format::from_file_name(&file_name);
+                        if name::validate_template(template).is_ok() {
+                            templates
+                                .entry(template.to_string())
+                                .and_modify(|source| {
+                                    if matches!(source, Source::BuiltIn) {
+                                        *source = Source::File(entry.path());
+                                    }
+                                })
+                                .or_insert_with(|| Source::File(entry.path()));
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(templates.into_iter().collect())
+    }
+
+    /// Prints the available templates with their descriptions.
+    pub fn print_list() -> Result<()> {
+        let mut builder = Builder::default();
+        builder.push_record(["TEMPLATE", "DESCRIPTION", "SOURCE"]);
+
+        for (name, source) in Self::list()? {
+            let description = match Self::load(&name) {
+                Ok((template, _)) => template.description.unwrap_or_default(),
+                Err(_) => format!("could not be loaded, see tend template show {name}"),
+            };
+            builder.push_record([name, description, source.to_string()]);
+        }
+
+        let mut table = builder.build();
+        table.with(Style::blank());
+        if colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.with(Modify::new(Rows::first()).with(Color::new("\x1b[1;34m", "\x1b[0m")));
+        }
+        println!("{table}");
+
+        Ok(())
+    }
+
+    /// Prints the complete definition of a template.
+    pub fn show(name: &str, format: ShowFormat) -> Result<()> {
+        let (template, source) = Self::load(name)?;
+        match format {
+            ShowFormat::Human => {
+                let unset = || "not set".to_string();
+
+                field("Template", name);
+                field(
+                    "Description",
+                    template.description.as_deref().unwrap_or("none"),
+                );
+                field("Source", &source.to_string());
+                if template.params.is_empty() {
+                    field("Parameters", "none");
+                } else {
+                    println!("{}", "Parameters:".thick());
+                    for (name, param) in &template.params {
+                        let default = param.default.as_ref().map_or_else(
+                            || "required".to_string(),
+                            |default| format!("default {default:?}"),
+                        );
+                        match &param.description {
+                            Some(description) => println!("  {name} ({default}): {description}"),
+                            None => println!("  {name} ({default})"),
+                        }
+                    }
+                }
+                field("Program", template.program.as_deref().unwrap_or("not set"));
+                field("Args", &format!("{:?}", template.args));
+                field(
+                    "Restart",
+                    &template
+                        .restart
+                        .map_or_else(unset, |restart| format!("{restart:?}")),
+                );
+                field(
+                    "Restart strategy",
+                    &template
+                        .restart_strategy
+                        .map_or_else(unset, |strategy| format!("{strategy:?}")),
+                );
+                field(
+                    "Max line length",
+                    &template
+                        .max_line_length
+                        .map_or_else(unset, |length| length.to_string()),
+                );
+                if template.env.is_empty() {
+                    field("Environment", "none");
+                } else {
+                    println!("{}", "Environment:".thick());
+                    for (key, value) in &template.env {
+                        println!("  {key}={value}");
+                    }
+                }
+                if template.event_hooks.is_empty() {
+                    field("Hooks", "none");
+                } else {
+                    println!("{}", "Hooks:".thick());
+                    for hook in &template.event_hooks {
+                        println!("  {hook}");
+                    }
+                }
+            }
+            ShowFormat::Json => println!("{}", serde_json::to_string_pretty(&template)?),
+            ShowFormat::Toml => print!("{}", toml::to_string_pretty(&template)?),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the template with its parameters replaced by the given values or their defaults.
+    pub fn expand(&self, template: &str, values: &BTreeMap<String, String>) -> Result<Self> {
+        if let Some(unknown) = values.keys().find(|key| !self.params.contains_key(*key)) {
+            anyhow::bail!("Template '{template}' has no parameter '{unknown}'.");
+        }
+        let values = self
+            .params
+            .iter()
+            .map(|(name, param)| {
+                values
+                    .get(name)
+                    .or(param.default.as_ref())
+                    .map(|value| (name.as_str(), value.as_str()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Template '{template}' needs a value for '{name}'; use --param {name}=VALUE."
+                        )
+                    })
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let expand = |text: &str| {
+            substitute(text, &values)
+                .with_context(|| format!("Invalid placeholder in template '{template}'"))
+        };
+
+        let mut expanded = self.clone();
+        expanded.program = self.program.as_deref().map(expand).transpose()?;
+        expanded.args = self
+            .args
+            .iter()
+            .map(|arg| expand(arg))
+            .collect::<Result<_>>()?;
+        for value in expanded.env.values_mut() {
+            *value = expand(value)?;
+        }
+        for hook in &mut expanded.event_hooks {
+            let Event::DetectSubstring { contains, .. } = &mut hook.event;
+            *contains = expand(contains)?;
+        }
+        Ok(expanded)
+    }
+
+    fn built_in(template: &str) -> Option<Self> {
         match template {
-            Template::PortForward => {
-                self.restart_strategy = RestartStrategy::ExponentialBackoff;
-
-                self.event_hooks.push(Hook {
-                    name: "lost connection hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "lost connection to pod".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::FastRestart,
-                });
-
-                self.event_hooks.push(Hook {
-                    name: "pending hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "Current status=Pending".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::FastRestart,
-                });
-
-                self.event_hooks.push(Hook {
-                    name: "timeout hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "Timeout occurred".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::FastRestart,
-                });
-
-                self.event_hooks.push(Hook {
-                    name: "container not running hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "container not running".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::Restart,
-                });
-
-                self.event_hooks.push(Hook {
-                    name: "connection forcibly closed hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "An existing connection was forcibly closed".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::FastRestart,
-                });
-
-                self.event_hooks.push(Hook {
-                    name: "no such container hook".to_string(),
-                    event: Event::DetectSubstring {
-                        contains: "No such container:".to_string(),
-                        stream: Stream::Any,
-                    },
-                    action: Action::Restart,
-                });
+            "dev-server" => Some(Self::dev_server()),
+            "docker-compose" => Some(Self::docker_compose()),
+            "kubectl-logs" => Some(Self::kubectl_logs()),
+            "port-forward" => Some(Self::port_forward()),
+            "ssh-tunnel" => Some(Self::ssh_tunnel()),
+            _ => None,
+        }
+    }
+
+    fn port_forward() -> Self {
+        Self {
+            description: Some("Keep kubectl port-forward connections alive".to_string()),
+            restart_strategy: Some(RestartStrategy::ExponentialBackoff),
+            event_hooks: vec![
+                hook(
+                    "lost connection hook",
+                    "lost connection to pod",
+                    Action::FastRestart,
+                ),
+                hook(
+                    "pending hook",
+                    "Current status=Pending",
+                    Action::FastRestart,
+                ),
+                hook("timeout hook", "Timeout occurred", Action::FastRestart),
+                hook(
+                    "container not running hook",
+                    "container not running",
+                    Action::Restart,
+                ),
+                hook(
+                    "connection forcibly closed hook",
+                    "An existing connection was forcibly closed",
+                    Action::FastRestart,
+                ),
+                hook(
+                    "no such container hook",
+                    "No such container:",
+                    Action::Restart,
+                ),
+            ],
+            ..Self::default()
+        }
+    }
+
+    fn ssh_tunnel() -> Self {
+        Self {
+            description: Some("Keep an ssh -L tunnel open".to_string()),
+            params: BTreeMap::from([
+                param("host", "Host to connect to, as accepted by ssh", None),
+                param("local", "Local port to listen on", None),
+                param("remote", "Address to forward to, as host:port", None),
+            ]),
+            program: Some("ssh".to_string()),
+            args: [
+                "-N",
+                "-o",
+                "ExitOnForwardFailure=yes",
+                "-o",
+                "ServerAliveInterval=30",
+                "-L",
+                "{local}:{remote}",
+                "{host}",
+            ]
+            .map(String::from)
+            .to_vec(),
+            restart_strategy: Some(RestartStrategy::ExponentialBackoff),
+            event_hooks: vec![
+                hook("broken pipe hook", "Broken pipe", Action::FastRestart),
+                hook(
+                    "connection reset hook",
+                    "Connection reset",
+                    Action::FastRestart,
+                ),
+                hook(
+                    "remote forwarding failed hook",
+                    "remote port forwarding failed",
+                    Action::Restart,
+                ),
+                hook(
+                    "timeout hook",
+                    "Timeout, server not responding",
+                    Action::FastRestart,
+                ),
+            ],
+            ..Self::default()
+        }
+    }
+
+    fn kubectl_logs() -> Self {
+        Self {
+            description: Some("Follow the logs of a Kubernetes resource".to_string()),
+            params: BTreeMap::from([
+                param("target", "Pod or resource, e.g. deploy/api", None),
+                param(
+                    "tail",
+                    "Lines of earlier output to show on each start",
+                    Some("10"),
+                ),
+            ]),
+            program: Some("kubectl".to_string()),
+            args: ["logs", "--follow", "--tail={tail}", "{target}"]
+                .map(String::from)
+                .to_vec(),
+            restart_strategy: Some(RestartStrategy::ExponentialBackoff),
+            event_hooks: vec![
+                hook("unexpected eof hook", "unexpected EOF", Action::FastRestart),
+                hook(
+                    "connection lost hook",
+                    "http2: client connection lost",
+                    Action::FastRestart,
+                ),
+                hook(
+                    "waiting to start hook",
+                    "is waiting to start",
+                    Action::Restart,
+                ),
+                hook(
+                    "container not found hook",
+                    "container not found",
+                    Action::Restart,
+                ),
+            ],
+            ..Self::default()
+        }
+    }
+
+    fn docker_compose() -> Self {
+        Self {
+            description: Some("Run docker compose up in the working directory".to_string()),
+            program: Some("docker".to_string()),
+            args: ["compose", "up"].map(String::from).to_vec(),
+            restart: Some(RestartBehavior::OnFailure),
+            restart_strategy: Some(RestartStrategy::ExponentialBackoff),
+            event_hooks: vec![
+                hook(
+                    "daemon not running hook",
+                    "Cannot connect to the Docker daemon",
+                    Action::Restart,
+                ),
+                hook(
+                    "address in use hook",
+                    "address already in use",
+                    Action::Restart,
+                ),
+                hook(
+                    "no configuration file hook",
+                    "no configuration file provided",
+                    Action::Stop,
+                ),
+            ],
+            ..Self::default()
+        }
+    }
+
+    fn dev_server() -> Self {
+        Self {
+            description: Some("Keep a file-watching development server running".to_string()),
+            restart_strategy: Some(RestartStrategy::ExponentialBackoff),
+            event_hooks: vec![
+                hook("address in use hook", "EADDRINUSE", Action::Restart),
+                hook(
+                    "out of memory hook",
+                    "JavaScript heap out of memory",
+                    Action::FastRestart,
+                ),
+                hook("nodemon crash hook", "app crashed", Action::FastRestart),
+                hook(
+                    "watcher limit hook",
+                    "System limit for number of file watchers reached",
+                    Action::Stop,
+                ),
+            ],
+            ..Self::default()
+        }
+    }
+}
+
+/// Hook of a built-in template that matches text on either output stream.
+fn hook(name: &str, contains: &str, action: Action) -> Hook {
+    Hook {
+        name: name.to_string(),
+        event: Event::DetectSubstring {
+            contains: contains.to_string(),
+            stream: Stream::Any,
+        },
+        action,
+    }
+}
+
+/// Parameter of a built-in template.
+fn param(name: &str, description: &str, default: Option<&str>) -> (String, Param) {
+    (
+        name.to_string(),
+        Param {
+            description: Some(description.to_string()),
+            default: default.map(String::from),
+        },
+    )
+}
+
+/// Replaces each `{NAME}` in the text with the value of the parameter.
+fn substitute(text: &str, values: &BTreeMap<&str, &str>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        let (before, from) = rest.split_at(start);
+        result.push_str(before);
+        if let Some(after) = from.strip_prefix("{{") {
+            result.push('{');
+            rest = after;
+        } else if let Some(after) = from.strip_prefix("}}") {
+            result.push('}');
+            rest = after;
+        } else if let Some(after) = from.strip_prefix('{')
+            && let Some((name, after)) = after.split_once('}')
+        {
+            let value = values
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("unknown parameter {{{name}}} in {text:?}"))?;
+            result.push_str(value);
+            rest = after;
+        } else {
+            anyhow::bail!("unmatched brace in {text:?}");
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+impl Job {
+    /// Copies the restart and output settings of a template into the job, for settings the
+    /// template defines.
+    pub const fn apply_template_settings(&mut self, template: &Template) {
+        if let Some(restart) = template.restart {
+            self.restart = restart;
+        }
+        if let Some(restart_strategy) = template.restart_strategy {
+            self.restart_strategy = restart_strategy;
+        }
+        if template.max_line_length.is_some() {
+            self.max_line_length = template.max_line_length;
+        }
+    }
+
+    /// Changes or removes the template of the job and applies its settings.
+    ///
+    /// A program and arguments that still match the old template are replaced with the new
+    /// template's, so that changed parameter values reach them.
+    pub fn set_template(
+        &mut self,
+        template: Option<String>,
+        params: BTreeMap<String, String>,
+    ) -> Result<()> {
+        let from_template = self.load_template().ok().flatten().is_some_and(|old| {
+            old.program.as_ref() == Some(&self.program) && old.args == self.args
+        });
+
+        self.template = template;
+        self.template_params = params;
+        let Some(template) = self.load_template()? else {
+            self.disabled_template_hooks.clear();
+            return Ok(());
+        };
+
+        self.apply_template_settings(&template);
+        if from_template && let Some(program) = template.program {
+            self.program = program;
+            self.args = template.args;
+        }
+        self.disabled_template_hooks.retain(|disabled| {
+            template
+                .event_hooks
+                .iter()
+                .any(|hook| &hook.name == disabled)
+        });
+        Ok(())
+    }
+
+    /// Loads the template the job refers to, if any, with the job's parameter values.
+    pub fn load_template(&self) -> Result<Option<Template>> {
+        self.template
+            .as_deref()
+            .map(|name| Template::load(name)?.0.expand(name, &self.template_params))
+            .transpose()
+    }
+
+    /// Whether the job's template has a hook with the given name.
+    pub fn template_hook(&self, hook: &str) -> Result<bool> {
+        Ok(self
+            .load_template()?
+            .is_some_and(|template| template.event_hooks.iter().any(|t| t.name == hook)))
+    }
+
+    /// Hooks of the job's template that apply to it: those it neither disabled nor replaced
+    /// with a hook of the same name.
+    pub fn active_template_hooks<'a>(&self, template: &'a Template) -> Vec<&'a Hook> {
+        template
+            .event_hooks
+            .iter()
+            .filter(|hook| {
+                !self.disabled_template_hooks.contains(&hook.name)
+                    && !self.event_hooks.iter().any(|own| own.name == hook.name)
+            })
+            .collect()
+    }
+
+    /// Layers the job's template under its own definition before it runs.
+    ///
+    /// Saved jobs only contain their own hooks and environment variables, so changes to a
+    /// template reach every job using it. Templates that cannot be loaded are reported and
+    /// skipped.
+    pub fn apply_template_layer(&mut self) {
+        let template = match self.load_template() {
+            Ok(Some(template)) => template,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("{}: {e:#}", self.name);
+                return;
             }
+        };
+
+        let hooks = self
+            .active_template_hooks(&template)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.event_hooks.extend(hooks);
+        for (key, value) in template.env {
+            self.env.entry(key).or_insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::event::ControlFlow;
+
+    /// A job using the hooks of a built-in template.
+    fn job(template: &str) -> Job {
+        let mut job: Job = serde_json::from_value(serde_json::json!({
+            "name": "test",
+            "group": "default",
+            "program": "true",
+            "args": [],
+            "working_directory": ".",
+        }))
+        .unwrap();
+        job.event_hooks = Template::built_in(template).unwrap().event_hooks;
+        job
+    }
+
+    #[test]
+    fn built_in_templates_load() {
+        for name in BUILT_IN {
+            assert!(Template::built_in(name).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn ssh_tunnel_hooks() {
+        let job = job("ssh-tunnel");
+        assert_eq!(
+            job.stderr_line_callback("Write failed: Broken pipe"),
+            ControlFlow::FastRestartCommand("broken pipe hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback("Connection reset by peer"),
+            ControlFlow::FastRestartCommand("connection reset hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback("Warning: remote port forwarding failed for listen port 5432"),
+            ControlFlow::RestartCommand("remote forwarding failed hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback("debug1: Connection established."),
+            ControlFlow::Nothing
+        );
+    }
+
+    #[test]
+    fn kubectl_logs_hooks() {
+        let job = job("kubectl-logs");
+        assert_eq!(
+            job.stderr_line_callback("error: unexpected EOF"),
+            ControlFlow::FastRestartCommand("unexpected eof hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback(
+                r#"Error from server (BadRequest): container "api" in pod "api-0" is waiting to start: ContainerCreating"#
+            ),
+            ControlFlow::RestartCommand("waiting to start hook")
+        );
+        assert_eq!(
+            job.stdout_line_callback("GET /health 200 1ms"),
+            ControlFlow::Nothing
+        );
+    }
+
+    #[test]
+    fn docker_compose_hooks() {
+        let job = job("docker-compose");
+        assert_eq!(
+            job.stderr_line_callback(
+                "Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?"
+            ),
+            ControlFlow::RestartCommand("daemon not running hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback("no configuration file provided: not found"),
+            ControlFlow::StopJob("no configuration file hook")
+        );
+        assert_eq!(
+            job.stdout_line_callback("db-1  | database system is ready to accept connections"),
+            ControlFlow::Nothing
+        );
+    }
+
+    #[test]
+    fn dev_server_hooks() {
+        let job = job("dev-server");
+        assert_eq!(
+            job.stderr_line_callback("Error: listen EADDRINUSE: address already in use :::3000"),
+            ControlFlow::RestartCommand("address in use hook")
+        );
+        assert_eq!(
+            job.stderr_line_callback(
+                "Error: ENOSPC: System limit for number of file watchers reached, watch '/app/src'"
+            ),
+            ControlFlow::StopJob("watcher limit hook")
+        );
+        assert_eq!(
+            job.stdout_line_callback("  VITE v5.0.0  ready in 300 ms"),
+            ControlFlow::Nothing
+        );
+    }
+
+    #[test]
+    fn port_forward_hooks() {
+        let job = job("port-forward");
+        assert_eq!(
+            job.stderr_line_callback(
+                "E0101 12:00:00.000000 portforward.go:413] lost connection to pod"
+            ),
+            ControlFlow::FastRestartCommand("lost connection hook")
+        );
+        assert_eq!(
+            job.stdout_line_callback("Forwarding from 127.0.0.1:5432 -> 5432"),
+            ControlFlow::Nothing
+        );
+    }
+
+    #[test]
+    fn parameters_expand_into_the_command() {
+        let template = Template::built_in("ssh-tunnel").unwrap();
+        let values = BTreeMap::from([
+            ("host".to_string(), "bastion".to_string()),
+            ("local".to_string(), "5432".to_string()),
+            ("remote".to_string(), "db:5432".to_string()),
+        ]);
+        let expanded = template.expand("ssh-tunnel", &values).unwrap();
+        assert_eq!(expanded.args.last().map(String::as_str), Some("bastion"));
+        assert!(expanded.args.contains(&"5432:db:5432".to_string()));
+
+        assert!(template.expand("ssh-tunnel", &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn yaml_templates_accept_tags_and_maps() {
+        let tagged = "event_hooks:\n- name: a\n  event: !DetectSubstring\n    stream: Any\n    contains: x\n  action: Stop\n";
+        let mapped = "event_hooks:\n- name: a\n  event:\n    DetectSubstring:\n      stream: Any\n      contains: x\n  action: Stop\n";
+        for contents in [tagged, mapped] {
+            let template: Template = Format::Yaml.parse_document(contents).unwrap();
+            assert_eq!(template.event_hooks.len(), 1, "{contents}");
         }
     }
+
+    #[test]
+    fn braces_can_be_escaped() {
+        let values = BTreeMap::from([("name", "api")]);
+        assert_eq!(substitute("{{{name}}}", &values).unwrap(), "{api}");
+        assert!(substitute("{other}", &values).is_err());
+        assert!(substitute("{name", &values).is_err());
+    }
 }
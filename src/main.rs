@@ -5,6 +5,9 @@
 #![deny(clippy::pedantic)]
 #![deny(clippy::nursery)]
 #![warn(clippy::cargo)]
+// Dependencies pull in two major versions of syn (process-wrap through futures-macro), which
+// cannot be fixed here.
+#![allow(clippy::multiple_crate_versions)]
 #![deny(missing_debug_implementations)]
 #![deny(unused_imports)]
 #![deny(unused_variables)]
@@ -86,12 +89,14 @@
 use crate::colors::Tend;
 mod args;
 mod colors;
+mod config;
 mod job;
 mod run;
 
 use crate::job::{Job, filter::Filter};
 use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
 
 fn standard_job_filter(
     name: Option<String>,
@@ -119,14 +124,50 @@ fn standard_job_filter(
     }
 }
 
+/// Reports a job that could not be saved because one with the same name exists.
+///
+/// Returns whether that was the case; other errors are passed on.
+fn already_exists(res: Result<()>) -> Result<bool> {
+    if let Err(ref error) = res
+        && let Some(error) = error.downcast_ref::<std::io::Error>()
+        && error.kind() == std::io::ErrorKind::AlreadyExists
+    {
+        eprintln!(
+            "{}",
+            "Job already exists. Use --overwrite to replace it.".failure()
+        );
+        return Ok(true);
+    }
+    res.map(|()| false)
+}
+
+fn set_enabled(filter: &Filter, enabled: bool, verbose: bool) -> Result<()> {
+    let mut names = vec![];
+    Job::iterate_jobs_filtered(|job| names.push(job.name), filter, true, verbose)?;
+    for name in names {
+        if let Err(e) = Job::update(&name, verbose, |job| {
+            job.enabled = enabled;
+            Ok(())
+        }) {
+            eprintln!("{} {}: {e}", name.job(), "could not be saved".failure());
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 #[allow(clippy::too_many_lines)]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let args = args::Cli::parse();
 
     if args.no_color {
         colored::control::set_override(false);
     }
+    if let Some(jobs_dir) = args.jobs_dir {
+        Job::set_jobs_dir(jobs_dir)?;
+    }
+    // Also covers NO_COLOR and output that is not a terminal.
+    let no_color = !colored::control::SHOULD_COLORIZE.should_colorize();
 
     let Some(command) = args.command else {
         let _ = <args::Cli as clap::CommandFactory>::command().print_help();
@@ -140,12 +181,13 @@ async fn main() -> Result<()> {
             job,
             exclude,
             name,
+            sources,
         } => {
             let filter = standard_job_filter(name, all, group, job, exclude);
 
-            match Job::list(&filter, args.verbose, args.no_color) {
+            match Job::list(&filter, sources.into(), args.verbose, no_color) {
                 Ok(()) => (),
-                Err(e) => eprintln!("Error: {e}"),
+                Err(e) => eprintln!("Error: {e:#}"),
             }
         }
         args::Commands::Run {
@@ -154,50 +196,60 @@ async fn main() -> Result<()> {
             job,
             all,
             exclude,
+            options,
         } => {
             let filter = standard_job_filter(name, all, group, job, exclude);
 
-            run::run(filter, args.verbose).await?;
+            return run::run(filter, &options, args.verbose).await;
         }
         args::Commands::Create {
             name,
-            program,
-            args,
-            restart,
-            group,
             overwrite,
-            restart_strategy,
-            template,
+            job,
         } => {
-            let mut job = Job {
-                name,
-                enabled: true,
-                program,
-                args,
-                restart,
-                group,
-                working_directory: std::env::current_dir()?,
-                restart_strategy,
-                event_hooks: vec![],
-                template,
-            };
+            let job = job.into_job(name)?;
+            job.validate()?;
 
-            if let Some(template) = template {
-                job.apply_template(template);
+            if already_exists(job.save(overwrite))? {
+                return Ok(ExitCode::SUCCESS);
             }
+        }
+        args::Commands::Exec {
+            name,
+            save,
+            overwrite,
+            output,
+            job,
+        } => {
+            let name = save.clone().or(name).unwrap_or_else(|| {
+                let program = job
+                    .program
+                    .as_deref()
+                    .or(job.template.as_deref())
+                    .unwrap_or_default();
+                std::path::Path::new(program).file_stem().map_or_else(
+                    || program.to_string(),
+                    |stem| stem.to_string_lossy().into_owned(),
+                )
+            });
+            let job = job.into_job(name)?;
+            job.validate()?;
 
-            let res = job.save(overwrite);
-            if let Err(ref error) = res
-                && let Some(error) = error.downcast_ref::<std::io::Error>()
-                && error.kind() == std::io::ErrorKind::AlreadyExists
-            {
-                eprintln!(
-                    "{}",
-                    "Job already exists. Use --overwrite to replace it.".failure()
-                );
-                return Ok(());
+            if save.is_some() {
+                job.save(overwrite)?;
             }
-            res?;
+
+            let options = args::RunOptions {
+                output,
+                fail_fast: false,
+                exit_on: vec![],
+                once: false,
+                restart: None,
+                restart_strategy: None,
+                format: job::output::OutputOptions::default(),
+                sources: args::JobSources::default(),
+            };
+            return run::run_jobs(vec![job], &options, args.verbose).await;
         }
         args::Commands::Enable {
             name,
@@ -208,15 +260,7 @@ async fn main() -> Result<()> {
         } => {
             let filter = standard_job_filter(name, all, group, job, exclude);
 
-            Job::iterate_jobs_filtered(
-                |mut job| {
-                    job.enabled = true;
-                    let _ = job.save(true);
-                },
-                &filter,
-                true,
-                args.verbose,
-            )?;
+            set_enabled(&filter, true, args.verbose)?;
         }
         args::Commands::Disable {
             name,
@@ -227,83 +271,251 @@ async fn main() -> Result<()> {
         } => {
             let filter = standard_job_filter(name, all, group, job, exclude);
 
-            Job::iterate_jobs_filtered(
-                |mut job| {
-                    job.enabled = false;
-                    let _ = job.save(true);
-                },
-                &filter,
-                true,
-                args.verbose,
-            )?;
+            set_enabled(&filter, false, args.verbose)?;
         }
         args::Commands::Edit { name, command } => {
-            let mut job = Job::load(&name, args.verbose).ok_or_else(|| {
-                anyhow::anyhow!("Job '{name}' was not found or could not be loaded.")
-            })?;
-            match command {
-                args::EditJobCommands::Group { group } => job.group = group,
-                args::EditJobCommands::Hook { command } => match command {
-                    args::EditJobHookCommands::List => {
-                        if job.event_hooks.is_empty() {
-                            println!("No hooks defined for job {}", job.name);
+            Job::update(&name, args.verbose, |job| {
+                match command {
+                    args::EditJobCommands::Group { group } => job.group = group,
+                    args::EditJobCommands::Program { program } => job.program = program,
+                    args::EditJobCommands::Args { command } => match command {
+                        args::EditJobArgsCommands::Append { args } => job.args.extend(args),
+                        args::EditJobArgsCommands::Replace { args } => job.args = args,
+                    },
+                    args::EditJobCommands::Cwd { path } => {
+                        job.working_directory = std::env::current_dir()?.join(path);
+                    }
+                    args::EditJobCommands::Restart { restart } => job.restart = restart,
+                    args::EditJobCommands::RestartStrategy { restart_strategy } => {
+                        job.restart_strategy = restart_strategy;
+                    }
+                    args::EditJobCommands::Template { template, param } => {
+                        job.set_template(template, param.into_iter().collect())?;
+                    }
+                    args::EditJobCommands::Env { set, unset } => {
+                        for key in unset {
+                            job.env.remove(&key);
+                        }
+                        job.env.extend(set);
+                    }
+                    args::EditJobCommands::MaxLineLength { max_line_length } => {
+                        job.max_line_length = max_line_length;
+                    }
+                    args::EditJobCommands::Color { color } => job.color = color,
+                    args::EditJobCommands::Output { reset, output } => {
+                        let current = if reset {
+                            job::output::OutputOptions::default()
                         } else {
+                            job.output.clone()
+                        };
+                        job.output = output.or(&current);
+                    }
+                    args::EditJobCommands::Hook { command } => match command {
+                        args::EditJobHookCommands::List => {
+                            let template = job.load_template()?.unwrap_or_default();
+                            if job.event_hooks.is_empty() && template.event_hooks.is_empty() {
+                                println!("No hooks defined for job {}", job.name);
+                            }
                             for hook in &job.event_hooks {
-                                let job::event::Event::DetectSubstring { stream, contains } =
-                                    &hook.event;
-                                let stream = match stream {
-                                    job::event::Stream::Stdout => "stdout",
-                                    job::event::Stream::Stderr => "stderr",
-                                    job::event::Stream::Any => "stdout or stderr",
-                                };
-                                let action = match &hook.action {
-                                    job::event::Action::Restart => "restart the job",
-                                    job::event::Action::FastRestart => {
-                                        "restart the job with the short backoff"
-                                    }
-                                    job::event::Action::Stop => "stop the job",
+                                println!("{hook}");
+                            }
+                            let active = job.active_template_hooks(&template);
+                            for hook in &template.event_hooks {
+                                let status = if active.iter().any(|active| active.name == hook.name)
+                                {
+                                    ""
+                                } else if job.disabled_template_hooks.contains(&hook.name) {
+                                    ", disabled"
+                                } else {
+                                    ", replaced by the job's hook"
                                 };
                                 println!(
-                                    "{}: when {} contains {:?}, {}",
-                                    hook.name, stream, contains, action
+                                    "{hook} (from template {}{status})",
+                                    job.template.as_deref().unwrap_or_default()
                                 );
                             }
                         }
-                    }
-                    args::EditJobHookCommands::Create { hook, t } => match t {
-                        args::JobHook::DetectSubstring {
-                            substring,
-                            stream,
-                            action,
-                        } => {
-                            job.event_hooks.push(job::event::Hook {
-                                name: hook,
-                                event: job::event::Event::DetectSubstring {
-                                    contains: substring,
-                                    stream,
-                                },
+                        args::EditJobHookCommands::Create { hook, t } => match t {
+                            args::JobHook::DetectSubstring {
+                                substring,
+                                stream,
                                 action,
+                            } => {
+                                job.event_hooks.push(job::event::Hook {
+                                    name: hook,
+                                    event: job::event::Event::DetectSubstring {
+                                        contains: substring,
+                                        stream,
+                                    },
+                                    action,
+                                });
+                            }
+                        },
+                        args::EditJobHookCommands::Delete { hook: hook_name } => {
+                            let mut deleted = false;
+                            job.event_hooks.retain(|hook| {
+                                let retain = hook.name != hook_name;
+                                if !retain {
+                                    deleted = true;
+                                    println!("Deleted hook {hook_name}");
+                                }
+                                retain
                             });
+
+                            if !deleted {
+                                if job.template_hook(&hook_name)? {
+                                    eprintln!(
+                                        "Hook {hook_name} comes from the job's template; use tend edit {} hook disable {hook_name:?} to stop it from applying",
+                                        job.name
+                                    );
+                                } else {
+                                    eprintln!("Hook {hook_name} not found");
+                                }
+                            }
                         }
-                    },
-                    args::EditJobHookCommands::Delete { hook: hook_name } => {
-                        let mut deleted = false;
-                        job.event_hooks.retain(|hook| {
-                            let retain = hook.name != hook_name;
-                            if !retain {
-                                deleted = true;
-                                println!("Deleted hook {hook_name}");
+                        args::EditJobHookCommands::Disable { hook } => {
+                            if !job.template_hook(&hook)? {
+                                anyhow::bail!(
+                                    "The template of job {} has no hook {hook}.",
+                                    job.name
+                                );
+                            }
+                            if job.disabled_template_hooks.contains(&hook) {
+                                println!("Hook {hook} is already disabled");
+                            } else {
+                                println!("Disabled hook {hook}");
+                                job.disabled_template_hooks.push(hook);
                             }
-                            retain
-                        });
-
-                        if !deleted {
-                            eprintln!("Hook {hook_name} not found");
                         }
+                        args::EditJobHookCommands::Enable { hook } => {
+                            if job.disabled_template_hooks.contains(&hook) {
+                                job.disabled_template_hooks
+                                    .retain(|disabled| disabled != &hook);
+                                println!("Enabled hook {hook}");
+                            } else if job.template_hook(&hook)? {
+                                println!("Hook {hook} is already enabled");
+                            } else {
+                                anyhow::bail!(
+                                    "The template of job {} has no hook {hook}.",
+                                    job.name
+                                );
+                            }
+                        }
+                    },
+                }
+                job.validate()
+            })?;
+        }
+        args::Commands::Convert {
+            name,
+            all,
+            group,
+            job,
+            exclude,
+            to,
+        } => {
+            let filter = standard_job_filter(name, all, group, job, exclude);
+
+            let mut result = Ok(());
+            Job::iterate_jobs_filtered(
+                |job| match job.save_as(to, true) {
+                    Ok(()) => println!("Converted {} to {}", job.name.job(), to.extension()),
+                    Err(e) => {
+                        eprintln!(
+                            "{} {}: {e}",
+                            job.name.job(),
+                            "could not be converted".failure()
+                        );
+                        result = Err(e);
                     }
                 },
+                &filter,
+                true,
+                args.verbose,
+            )?;
+            result?;
+        }
+        args::Commands::Migrate {
+            name,
+            all,
+            group,
+            job,
+            exclude,
+            dry_run,
+        } => {
+            let filter = standard_job_filter(name, all, group, job, exclude);
+
+            Job::migrate_saved(&filter, dry_run, args.verbose)?;
+        }
+        args::Commands::Template { command } => match command {
+            args::TemplateCommands::List => job::template::Template::print_list()?,
+            args::TemplateCommands::Show { name, format } => {
+                job::template::Template::show(&name, format)?;
+            }
+        },
+        args::Commands::Schema { project, template } => {
+            let schema = if project {
+                job::schema::project()
+            } else if template {
+                job::schema::template()
+            } else {
+                job::schema::job()
+            };
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        args::Commands::Rename {
+            name,
+            new_name,
+            overwrite,
+        } => {
+            if already_exists(Job::rename(
+                &name,
+                new_name.clone(),
+                overwrite,
+                args.verbose,
+            ))? {
+                return Ok(ExitCode::FAILURE);
+            }
+            println!("Renamed {} to {}", name.job(), new_name.job());
+        }
+        args::Commands::Clone {
+            name,
+            new_name,
+            overwrite,
+            group,
+            program,
+            cwd,
+            disabled,
+            args: job_args,
+        } => {
+            let copied = Job::copy(&name, &new_name, overwrite, args.verbose, |job| {
+                if let Some(group) = group {
+                    job.group = group;
+                }
+                if let Some(program) = program {
+                    job.program = program;
+                }
+                if let Some(cwd) = cwd {
+                    job.working_directory = std::env::current_dir()?.join(cwd);
+                }
+                if disabled {
+                    job.enabled = false;
+                }
+                if let Some(job_args) = job_args {
+                    job.args = job_args;
+                }
+                job.validate()
+            });
+            if already_exists(copied)? {
+                return Ok(ExitCode::FAILURE);
+            }
+            println!("Copied {} to {}", name.job(), new_name.job());
+        }
+        args::Commands::Show { name, format } => Job::show(&name, format, args.verbose)?,
+        args::Commands::Open { name } => {
+            if Job::open_in_editor(&name)? {
+                println!("Saved {}", name.job());
             }
-            job.save(true)?;
         }
         args::Commands::Delete {
             name,
@@ -314,7 +526,7 @@ async fn main() -> Result<()> {
             exclude,
         } => {
             let unchecked = group.is_empty();
-            let filter = standard_job_filter(name, all, group, job, exclude);
+            let filter = standard_job_filter(name.clone(), all, group, job, exclude);
 
             if all && !confirm {
                 eprintln!(
@@ -326,10 +538,18 @@ async fn main() -> Result<()> {
                 if unchecked {
                     if all {
                         Job::delete_all_unchecked()?;
+                    } else if let Some(name) = name {
+                        if Job::find_file(&name)?.is_none() {
+                            eprintln!("{}", format!("Job '{name}' was not found.").failure());
+                            return Ok(ExitCode::FAILURE);
+                        }
+                        Job::delete_unchecked(&name)?;
                     } else {
                         Job::iterate_job_names_filtered(
                             |job_name| {
-                                let _ = Job::delete_unchecked(job_name);
+                                if let Err(e) = Job::delete_unchecked(job_name) {
+                                    eprintln!("{} could not be deleted: {e}", job_name.job());
+                                }
                             },
                             &filter,
                             args.verbose,
@@ -338,7 +558,9 @@ async fn main() -> Result<()> {
                 } else {
                     Job::iterate_jobs_filtered(
                         |job| {
-                            let _ = job.delete();
+                            if let Err(e) = job.delete() {
+                                eprintln!("{} could not be deleted: {e}", job.name.job());
+                            }
                         },
                         &filter,
                         true,
@@ -349,5 +571,5 @@ async fn main() -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
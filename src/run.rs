@@ -1,49 +1,250 @@
-use crate::{Job, job::filter::Filter};
+use crate::{
+    Job,
+    args::RunOptions,
+    colors::Tend,
+    config::Config,
+    job::{
+        event::{Action, RestartBehavior},
+        filter::Filter,
+        outcome::{Outcome, StopReason},
+        output::{OutputMode, Printer},
+    },
+};
+use folktime::Folktime;
+use std::{collections::HashMap, process::ExitCode};
+use tabled::{
+    builder::Builder,
+    settings::{Color, Modify, Style, object::Rows},
+};
 use tokio::sync::mpsc;
 
-pub async fn run(job_filter: Filter, verbose: bool) -> anyhow::Result<()> {
+/// Exit code of `tend run` when it was interrupted and no job failed.
+const INTERRUPTED: u8 = 130;
+
+pub async fn run(
+    job_filter: Filter,
+    options: &RunOptions,
+    verbose: bool,
+) -> anyhow::Result<ExitCode> {
+    let jobs = Job::collect(&job_filter, false, options.sources.into(), verbose)?;
+
+    if jobs.is_empty() {
+        anyhow::bail!("No enabled jobs matched.");
+    }
+
+    run_jobs(jobs, options, verbose).await
+}
+
+#[allow(clippy::too_many_lines)]
+pub async fn run_jobs(
+    mut jobs: Vec<Job>,
+    options: &RunOptions,
+    verbose: bool,
+) -> anyhow::Result<ExitCode> {
+    let mode = options.output;
     let mut join_set = tokio::task::JoinSet::new();
     let mut cancel_handles = vec![];
 
-    let mut count = 0;
+    let mut names = HashMap::new();
 
-    Job::iterate_jobs_filtered(
-        |job| {
-            count += 1;
+    for job in &mut jobs {
+        job.apply_template_layer();
+        apply_overrides(job, options);
+    }
 
-            let (tx, rx) = mpsc::channel::<()>(1);
-            cancel_handles.push(tx);
-            join_set.spawn(job.create_repeated_process(rx, verbose));
-        },
-        &job_filter,
-        false,
-        verbose,
-    )?;
+    for name in &options.exit_on {
+        if !jobs.iter().any(|job| &job.name == name) {
+            anyhow::bail!("--exit-on job '{name}' is not among the jobs being run.");
+        }
+    }
 
-    if count == 0 {
-        anyhow::bail!("No enabled jobs matched.");
+    let config = Config::load()?;
+
+    jobs.sort_by(|a, b| a.name.cmp(&b.name));
+    let name_width = jobs.iter().map(|job| job.name.len()).max().unwrap_or(0);
+    let colors = config.colors.assign(
+        &jobs
+            .iter()
+            .map(|job| (job.name.as_str(), job.color))
+            .collect::<Vec<_>>(),
+    );
+
+    for (job, color) in jobs.into_iter().zip(colors) {
+        let format = options.format.clone().or(&job.output);
+        let printer = Printer::new(
+            &job.name,
+            &format,
+            name_width,
+            color,
+            config.colors.stderr,
+            mode,
+            verbose,
+        );
+
+        let (tx, rx) = mpsc::channel::<()>(1);
+        cancel_handles.push(tx);
+        let name = job.name.clone();
+        let handle = join_set.spawn(job.create_repeated_process(rx, printer));
+        names.insert(handle.id(), name);
     }
 
+    let mut stopping = false;
+    let mut interrupted = false;
+    let mut outcomes = vec![];
+
     loop {
         tokio::select! {
-            a = join_set.join_next() => {
-                if a.is_none() {
-                    if verbose {
-                        println!("All jobs finished.");
+            a = join_set.join_next_with_id() => {
+                match a {
+                    None => {
+                        if verbose && mode == OutputMode::Text {
+                            println!("All jobs finished.");
+                        }
+                        break;
+                    }
+                    Some(Ok((_, outcome))) => {
+                        if !stopping {
+                            let reason = if options.fail_fast && outcome.failed() {
+                                Some("failed")
+                            } else if options.exit_on.contains(&outcome.job) {
+                                Some("stopped")
+                            } else {
+                                None
+                            };
+
+                            if let Some(reason) = reason {
+                                stopping = true;
+                                report_stop_all(&outcome.job, reason, mode);
+                                stop_all(&cancel_handles);
+                            }
+                        }
+                        outcomes.push(outcome);
+                    }
+                    Some(Err(e)) => {
+                        let mut outcome = Outcome::new(names.remove(&e.id()).unwrap_or_default());
+                        if e.is_panic() {
+                            outcome.stop_reason = StopReason::Error("panicked".to_string());
+                        }
+                        outcomes.push(outcome);
                     }
-                    break;
                 }
             }
 
             _ = tokio::signal::ctrl_c() => {
-                for tx in &cancel_handles {
-                    let _ = tx.send(()).await;
+                if interrupted {
+                    // Second Ctrl+C: do not wait for jobs to stop gracefully.
+                    join_set.abort_all();
+                } else {
+                    stopping = true;
+                    interrupted = true;
+                    stop_all(&cancel_handles);
                 }
+            }
+        }
+    }
 
-                join_set.shutdown().await;
+    outcomes.sort_by(|a, b| a.job.cmp(&b.job));
+    match mode {
+        OutputMode::Text => print_summary(&outcomes),
+        OutputMode::Json => print_summary_json(&outcomes),
+    }
+
+    Ok(if outcomes.iter().any(Outcome::failed) {
+        ExitCode::FAILURE
+    } else if interrupted {
+        ExitCode::from(INTERRUPTED)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Applies the restart options of this run to a job without saving them.
+fn apply_overrides(job: &mut Job, options: &RunOptions) {
+    if options.once {
+        job.restart = RestartBehavior::Never;
+        for hook in &mut job.event_hooks {
+            if matches!(hook.action, Action::Restart | Action::FastRestart) {
+                hook.action = Action::Stop;
             }
         }
     }
+    if let Some(restart) = options.restart {
+        job.restart = restart;
+    }
+    if let Some(restart_strategy) = options.restart_strategy {
+        job.restart_strategy = restart_strategy;
+    }
+}
 
-    Ok(())
+fn stop_all(cancel_handles: &[mpsc::Sender<()>]) {
+    for tx in cancel_handles {
+        let _ = tx.try_send(());
+    }
+}
+
+fn report_stop_all(job: &str, reason: &str, mode: OutputMode) {
+    match mode {
+        OutputMode::Text => println!("{} {reason}, stopping all jobs", job.job()),
+        OutputMode::Json => println!(
+            "{}",
+            serde_json::json!({
+                "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "job": job,
+                "event": "stopping_all",
+                "reason": reason,
+            })
+        ),
+    }
+}
+
+fn print_summary_json(outcomes: &[Outcome]) {
+    for outcome in outcomes {
+        println!(
+            "{}",
+            serde_json::json!({
+                "timestamp": chrono::Utc::now()
+                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "job": outcome.job,
+                "event": "summary",
+                "runtime_ms": u64::try_from(outcome.runtime.as_millis()).unwrap_or(u64::MAX),
+                "restarts": outcome.restarts,
+                "exit_code": outcome.last_status.and_then(|status| status.code()),
+                "status": outcome.last_status.map(|status| status.to_string()),
+                "stop_reason": outcome.stop_reason.to_string(),
+                "failed": outcome.failed(),
+            })
+        );
+    }
+}
+
+fn print_summary(outcomes: &[Outcome]) {
+    let mut builder = Builder::default();
+    builder.push_record(["JOB", "RUNTIME", "RESTARTS", "LAST EXIT", "STOP REASON"]);
+
+    for outcome in outcomes {
+        builder.push_record([
+            outcome.job.clone(),
+            Folktime::duration(outcome.runtime).to_string(),
+            outcome.restarts.to_string(),
+            outcome
+                .last_status
+                .map_or_else(|| "-".to_string(), |status| status.to_string()),
+            outcome.stop_reason.to_string(),
+        ]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::blank());
+    if colored::control::SHOULD_COLORIZE.should_colorize() {
+        table.with(Modify::new(Rows::first()).with(Color::new("\x1b[1;34m", "\x1b[0m")));
+    }
+
+    let failed = outcomes.iter().filter(|outcome| outcome.failed()).count();
+    println!("{table}");
+    if failed != 0 {
+        println!(
+            "{}",
+            format!("{failed} of {} jobs failed", outcomes.len()).failure()
+        );
+    }
 }